@@ -0,0 +1,269 @@
+use crate::{
+    diagnostic::ErrorFormat,
+    lint::{Level, LintConfig},
+};
+
+pub const USAGE: &str = "\
+Usage: rustylox [options] [script | -]
+       rustylox [options] -e <program>
+       rustylox [options] <command> [args]
+
+Commands:
+  run <script>      Run a script
+  tokens <script>   Print the tokens of a script
+  check <script>    Scan and lint a script without running it
+
+Options:
+  --error-format=<human|json>   How to print diagnostics
+  -A, -W, -D <lint>             Allow, warn about or deny a lint or group
+  -e <program>                  Run a program given on the command line
+  --explain <code>              Explain a diagnostic code
+  -h, --help                    Print help, or a command's help
+
+A script of `-` is read from standard input. With no script, rustylox
+starts an interactive prompt, or runs standard input if it is not a
+terminal.
+";
+
+const RUN_USAGE: &str = "\
+Usage: rustylox run [options] <script>
+
+Runs a script. `rustylox <script>` is a shorthand for this command.
+";
+
+const TOKENS_USAGE: &str = "\
+Usage: rustylox tokens [options] [--format=<table|json>] <script>
+
+Prints each token of a script with its location. The table format is
+meant for people; the JSON format prints one object per line.
+";
+
+const CHECK_USAGE: &str = "\
+Usage: rustylox check [options] <script>
+
+Reports scan errors and lint warnings without running the script. Exits
+with status 1 if there are errors or denied lints.
+";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenFormat {
+    Table,
+    Json,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Repl,
+    Run { path: String },
+    Eval { source: String },
+    Tokens { path: String, format: TokenFormat },
+    Check { path: String },
+    Explain { code: String },
+    Help { usage: &'static str },
+}
+
+pub struct Options {
+    pub command: Command,
+    pub error_format: ErrorFormat,
+    pub lints: LintConfig,
+}
+
+/// Parses the arguments after the program name. Options may appear before
+/// or after the command.
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+    let mut error_format = ErrorFormat::Human;
+    let mut lints = LintConfig::default();
+    let mut token_format = None;
+    let mut help = false;
+    let mut explain = None;
+    let mut eval = None;
+    let mut positional = Vec::new();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let level = match arg.as_str() {
+            "-A" => Some(Level::Allow),
+            "-W" => Some(Level::Warn),
+            "-D" => Some(Level::Deny),
+            _ => None,
+        };
+        if let Some(level) = level {
+            let name = args
+                .next()
+                .ok_or_else(|| format!("Missing lint name after '{}'", arg))?;
+            lints.set(&name, level)?;
+        } else if arg == "-h" || arg == "--help" {
+            help = true;
+        } else if arg == "-e" {
+            eval = Some(args.next().ok_or("Missing program after '-e'")?);
+        } else if arg == "--explain" {
+            explain = Some(args.next().ok_or("Missing code after '--explain'")?);
+        } else if let Some(format) = arg.strip_prefix("--error-format=") {
+            error_format = match format {
+                "human" => ErrorFormat::Human,
+                "json" => ErrorFormat::Json,
+                _ => return Err(format!("Unknown error format '{}'", format)),
+            };
+        } else if let Some(format) = arg.strip_prefix("--format=") {
+            token_format = Some(match format {
+                "table" => TokenFormat::Table,
+                "json" => TokenFormat::Json,
+                _ => return Err(format!("Unknown token format '{}'", format)),
+            });
+        } else if arg.starts_with('-') && arg != "-" {
+            return Err(format!("Unknown option '{}'", arg));
+        } else {
+            positional.push(arg);
+        }
+    }
+
+    let (name, rest) = match positional.split_first() {
+        Some((name, rest)) if ["run", "tokens", "check"].contains(&name.as_str()) => {
+            (Some(name.as_str()), rest)
+        }
+        _ => (None, positional.as_slice()),
+    };
+    if token_format.is_some() && name != Some("tokens") {
+        return Err("'--format' is only valid for the tokens command".to_string());
+    }
+
+    let command = if help {
+        Command::Help {
+            usage: match name {
+                Some("run") => RUN_USAGE,
+                Some("tokens") => TOKENS_USAGE,
+                Some("check") => CHECK_USAGE,
+                _ => USAGE,
+            },
+        }
+    } else if let Some(code) = explain {
+        Command::Explain { code }
+    } else if let Some(source) = eval {
+        match positional.first() {
+            Some(extra) => return Err(format!("Unexpected argument '{}'", extra)),
+            None => Command::Eval { source },
+        }
+    } else {
+        match (name, rest) {
+            (None, []) => Command::Repl,
+            (None | Some("run"), [path]) => Command::Run { path: path.clone() },
+            (Some("tokens"), [path]) => Command::Tokens {
+                path: path.clone(),
+                format: token_format.unwrap_or(TokenFormat::Table),
+            },
+            (Some("check"), [path]) => Command::Check { path: path.clone() },
+            (Some(_), [_]) => unreachable!("commands are matched above"),
+            (Some(name), []) => return Err(format!("Missing script for '{}'", name)),
+            (_, [_, extra, ..]) => return Err(format!("Unexpected argument '{}'", extra)),
+        }
+    };
+
+    Ok(Options {
+        command,
+        error_format,
+        lints,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        cli::{parse, Command, TokenFormat, CHECK_USAGE, USAGE},
+        diagnostic::ErrorFormat,
+        lint::{Level, Lint},
+    };
+
+    fn command(args: &[&str]) -> Result<Command, String> {
+        parse(args.iter().map(|arg| arg.to_string())).map(|options| options.command)
+    }
+
+    #[test]
+    fn defaults_to_the_repl_and_bare_scripts() {
+        assert_eq!(command(&[]), Ok(Command::Repl));
+        assert_eq!(
+            command(&["main.lox"]),
+            Ok(Command::Run {
+                path: "main.lox".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn parses_subcommands() {
+        assert_eq!(
+            command(&["run", "main.lox"]),
+            Ok(Command::Run {
+                path: "main.lox".to_string()
+            })
+        );
+        assert_eq!(
+            command(&["tokens", "--format=json", "main.lox"]),
+            Ok(Command::Tokens {
+                path: "main.lox".to_string(),
+                format: TokenFormat::Json,
+            })
+        );
+        assert_eq!(
+            command(&["check", "main.lox"]),
+            Ok(Command::Check {
+                path: "main.lox".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn parses_global_options_anywhere() {
+        let options = parse(
+            [
+                "check",
+                "main.lox",
+                "--error-format=json",
+                "-D",
+                "empty_block",
+            ]
+            .iter()
+            .map(|arg| arg.to_string()),
+        )
+        .unwrap();
+        assert_eq!(options.error_format, ErrorFormat::Json);
+        assert_eq!(options.lints.level(Lint::EmptyBlock), Level::Deny);
+    }
+
+    #[test]
+    fn parses_inline_programs_and_stdin() {
+        assert_eq!(
+            command(&["-e", "print 1;"]),
+            Ok(Command::Eval {
+                source: "print 1;".to_string()
+            })
+        );
+        assert_eq!(
+            command(&["-"]),
+            Ok(Command::Run {
+                path: "-".to_string()
+            })
+        );
+        assert!(command(&["-e", "print 1;", "main.lox"]).is_err());
+        assert!(command(&["-e"]).is_err());
+    }
+
+    #[test]
+    fn prints_help_per_command() {
+        assert_eq!(command(&["--help"]), Ok(Command::Help { usage: USAGE }));
+        assert_eq!(
+            command(&["check", "-h"]),
+            Ok(Command::Help { usage: CHECK_USAGE })
+        );
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert!(command(&["a.lox", "b.lox"]).is_err());
+        assert!(command(&["tokens"]).is_err());
+        assert!(command(&["--format=json", "a.lox"]).is_err());
+        assert!(command(&["--bogus"]).is_err());
+        assert!(command(&["-D"]).is_err());
+        assert!(command(&["-W", "bogus", "a.lox"]).is_err());
+        assert!(command(&["-D", "unused", "a.lox"]).is_err());
+    }
+}
@@ -0,0 +1,505 @@
+use crate::source_map::{SourceMap, Span};
+use std::{
+    fmt,
+    io::{self, Write},
+};
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    fn color(&self) -> &'static str {
+        match self {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// How diagnostics are written: rendered snippets for people, or one JSON
+/// object per line for tools.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorFormat {
+    Human,
+    Json,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: Option<String>,
+}
+
+/// A machine-applicable fix: replace `span` with `replacement`.
+#[derive(Debug, PartialEq)]
+pub struct Suggestion {
+    pub span: Span,
+    pub message: String,
+    pub replacement: String,
+}
+
+/// A message about a span of source, renderable as a rustc-style snippet.
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic {
+    pub code: Option<&'static str>,
+    pub severity: Severity,
+    pub message: String,
+    pub primary: Label,
+    pub secondary: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+    pub suggestions: Vec<Suggestion>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl Into<String>, span: Span) -> Self {
+        Diagnostic {
+            code: None,
+            severity,
+            message: message.into(),
+            primary: Label {
+                span,
+                message: None,
+            },
+            secondary: Vec::new(),
+            notes: Vec::new(),
+            help: None,
+            suggestions: Vec::new(),
+        }
+    }
+
+    pub fn error(message: impl Into<String>, span: Span) -> Self {
+        Diagnostic::new(Severity::Error, message, span)
+    }
+
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_label(mut self, message: impl Into<String>) -> Self {
+        self.primary.message = Some(message.into());
+        self
+    }
+
+    pub fn with_secondary(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.secondary.push(Label {
+            span,
+            message: Some(message.into()),
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    pub fn with_suggestion(
+        mut self,
+        span: Span,
+        message: impl Into<String>,
+        replacement: impl Into<String>,
+    ) -> Self {
+        self.suggestions.push(Suggestion {
+            span,
+            message: message.into(),
+            replacement: replacement.into(),
+        });
+        self
+    }
+
+    pub fn emit(
+        &self,
+        sources: &SourceMap,
+        format: ErrorFormat,
+        color: bool,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        match format {
+            ErrorFormat::Human => self.render(sources, color, out),
+            ErrorFormat::Json => writeln!(out, "{}", self.to_json(sources)),
+        }
+    }
+
+    /// Writes the diagnostic with the offending source lines and markers
+    /// under each labelled span, using ANSI colors when `color` is set.
+    pub fn render(&self, sources: &SourceMap, color: bool, out: &mut dyn Write) -> io::Result<()> {
+        let paint = |style: &str, text: &str| {
+            if color {
+                format!("{}{}{}", style, text, RESET)
+            } else {
+                text.to_string()
+            }
+        };
+
+        let mut marks: Vec<Mark> = std::iter::once((&self.primary, true))
+            .chain(self.secondary.iter().map(|label| (label, false)))
+            .map(|(label, primary)| Mark::new(sources, label, primary))
+            .collect();
+        marks.sort_by_key(|mark| (mark.line, mark.column));
+        let width = marks
+            .iter()
+            .map(|mark| mark.line.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let pad = " ".repeat(width);
+        let gutter = |line: &str| paint(BLUE, &format!("{:>width$} |", line, width = width));
+
+        let severity = match self.code {
+            Some(code) => format!("{}[{}]", self.severity, code),
+            None => self.severity.to_string(),
+        };
+        writeln!(
+            out,
+            "{}{}",
+            paint(self.severity.color(), &severity),
+            paint(BOLD, &format!(": {}", self.message))
+        )?;
+        writeln!(
+            out,
+            "{}{} {}",
+            pad,
+            paint(BLUE, "-->"),
+            sources.location(self.primary.span)
+        )?;
+        writeln!(out, "{}", gutter(""))?;
+
+        let mut previous_line = None;
+        for mark in &marks {
+            if previous_line != Some(mark.line) {
+                if previous_line.is_some_and(|previous| mark.line > previous + 1) {
+                    writeln!(out, "{}", paint(BLUE, "..."))?;
+                }
+                let text = sources.get(mark.span.file).line(mark.line);
+                writeln!(out, "{} {}", gutter(&mark.line.to_string()), text)?;
+                previous_line = Some(mark.line);
+            }
+
+            let text = sources.get(mark.span.file).line(mark.line);
+            let indent: String = text
+                .chars()
+                .take(mark.column - 1)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let (style, symbol) = if mark.primary {
+                (self.severity.color(), "^")
+            } else {
+                (BLUE, "-")
+            };
+            let underline = symbol.repeat(mark.width);
+            let label = match &mark.message {
+                Some(message) => format!("{} {}", underline, message),
+                None => underline,
+            };
+            writeln!(out, "{} {}{}", gutter(""), indent, paint(style, &label))?;
+        }
+
+        for note in &self.notes {
+            writeln!(out, "{} {} {}", pad, paint(BOLD, "= note:"), note)?;
+        }
+        if let Some(help) = &self.help {
+            writeln!(out, "{} {} {}", pad, paint(BOLD, "= help:"), help)?;
+        }
+        for suggestion in &self.suggestions {
+            writeln!(
+                out,
+                "{} {} {}: `{}`",
+                pad,
+                paint(BOLD, "= help:"),
+                suggestion.message,
+                suggestion.apply(sources).trim_start()
+            )?;
+        }
+        writeln!(out)
+    }
+
+    /// Serializes the diagnostic as a single-line JSON object.
+    pub fn to_json(&self, sources: &SourceMap) -> String {
+        let related: Vec<String> = self
+            .secondary
+            .iter()
+            .map(|label| {
+                format!(
+                    "{{\"message\":{},{}}}",
+                    json_option(&label.message),
+                    json_span(sources, label.span)
+                )
+            })
+            .collect();
+        let notes: Vec<String> = self.notes.iter().map(|note| json_string(note)).collect();
+        let fixes: Vec<String> = self
+            .suggestions
+            .iter()
+            .map(|suggestion| {
+                format!(
+                    "{{\"message\":{},{},\"replacement\":{}}}",
+                    json_string(&suggestion.message),
+                    json_span(sources, suggestion.span),
+                    json_string(&suggestion.replacement)
+                )
+            })
+            .collect();
+        format!(
+            "{{\"code\":{},\"severity\":{},\"message\":{},{},\"label\":{},\
+             \"related\":[{}],\"notes\":[{}],\"help\":{},\"fixes\":[{}]}}",
+            self.code.map_or("null".to_string(), json_string),
+            json_string(&self.severity.to_string()),
+            json_string(&self.message),
+            json_span(sources, self.primary.span),
+            json_option(&self.primary.message),
+            related.join(","),
+            notes.join(","),
+            json_option(&self.help),
+            fixes.join(",")
+        )
+    }
+}
+
+impl Suggestion {
+    /// Returns the suggestion's first line with the replacement spliced in.
+    fn apply(&self, sources: &SourceMap) -> String {
+        let file = sources.get(self.span.file);
+        let line = file.location(self.span.start).line;
+        let text = file.line(line);
+        let line_start = file.line_start(line);
+        let start = self.span.start - line_start;
+        let end = (self.span.end - line_start).min(text.len());
+        format!("{}{}{}", &text[..start], self.replacement, &text[end..])
+    }
+}
+
+fn json_span(sources: &SourceMap, span: Span) -> String {
+    let file = sources.get(span.file);
+    let start = file.location(span.start);
+    let end = file.location(span.end);
+    format!(
+        "\"file\":{},\"start\":{{\"line\":{},\"column\":{},\"offset\":{}}},\
+         \"end\":{{\"line\":{},\"column\":{},\"offset\":{}}}",
+        json_string(start.file),
+        start.line,
+        start.column,
+        span.start,
+        end.line,
+        end.column,
+        span.end
+    )
+}
+
+fn json_option(value: &Option<String>) -> String {
+    match value {
+        Some(value) => json_string(value),
+        None => "null".to_string(),
+    }
+}
+
+pub fn json_string(value: &str) -> String {
+    let mut json = String::with_capacity(value.len() + 2);
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// A label resolved to the line and columns it underlines. Spans running
+/// past the end of their first line are cut off there.
+struct Mark<'a> {
+    span: Span,
+    message: &'a Option<String>,
+    primary: bool,
+    line: usize,
+    column: usize,
+    width: usize,
+}
+
+impl<'a> Mark<'a> {
+    fn new(sources: &SourceMap, label: &'a Label, primary: bool) -> Self {
+        let file = sources.get(label.span.file);
+        let start = file.location(label.span.start);
+        let end = file.location(label.span.end);
+        let last_column = file.line(start.line).chars().count() + 1;
+        let end_column = if end.line == start.line {
+            end.column
+        } else {
+            last_column
+        };
+        Mark {
+            span: label.span,
+            message: &label.message,
+            primary,
+            line: start.line,
+            column: start.column,
+            width: end_column.saturating_sub(start.column).max(1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        diagnostic::{json_string, Diagnostic},
+        source_map::{SourceMap, Span},
+    };
+
+    fn render(sources: &SourceMap, diagnostic: &Diagnostic, color: bool) -> String {
+        let mut out = Vec::new();
+        diagnostic.render(sources, color, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn underlines_the_primary_span() {
+        let mut sources = SourceMap::new();
+        let file = sources.add("main.lox", "var a = 1;\nvar b = a @ 2;\n");
+        let diagnostic = Diagnostic::error("Unexpected character: @", Span::new(file, 21, 22))
+            .with_label("here");
+        assert_eq!(
+            render(&sources, &diagnostic, false),
+            "error: Unexpected character: @\n \
+             --> main.lox:2:11\n  \
+             |\n\
+             2 | var b = a @ 2;\n  \
+             |           ^ here\n\n"
+        );
+    }
+
+    #[test]
+    fn renders_secondary_labels_notes_and_help() {
+        let mut sources = SourceMap::new();
+        let source = "print \"a\n\n\nb";
+        let file = sources.add("main.lox", source);
+        let diagnostic = Diagnostic::error("Unterminated string", Span::new(file, 12, 12))
+            .with_label("source ends here")
+            .with_secondary(Span::new(file, 6, 7), "string starts here")
+            .with_note("strings may span lines")
+            .with_help("add a closing `\"`");
+        assert_eq!(
+            render(&sources, &diagnostic, false),
+            "error: Unterminated string\n \
+             --> main.lox:4:2\n  \
+             |\n\
+             1 | print \"a\n  \
+             |       - string starts here\n\
+             ...\n\
+             4 | b\n  \
+             |  ^ source ends here\n  \
+             = note: strings may span lines\n  \
+             = help: add a closing `\"`\n\n"
+        );
+    }
+
+    #[test]
+    fn keeps_tabs_when_aligning_markers() {
+        let mut sources = SourceMap::new();
+        let file = sources.add("main.lox", "\tfoo bar");
+        let diagnostic = Diagnostic::error("Bad", Span::new(file, 5, 8));
+        let rendered = render(&sources, &diagnostic, false);
+        assert!(rendered.contains("1 | \tfoo bar\n  | \t    ^^^\n"));
+    }
+
+    #[test]
+    fn truncates_multi_line_spans_at_the_end_of_the_first_line() {
+        let mut sources = SourceMap::new();
+        let file = sources.add("main.lox", "\"ab\ncd");
+        let diagnostic = Diagnostic::error("Bad", Span::new(file, 0, 6));
+        let rendered = render(&sources, &diagnostic, false);
+        assert!(rendered.contains("1 | \"ab\n  | ^^^\n"));
+    }
+
+    #[test]
+    fn shows_suggestions_applied_to_the_line() {
+        let mut sources = SourceMap::new();
+        let file = sources.add("main.lox", "var x = 12.;");
+        let diagnostic = Diagnostic::error("Bad", Span::new(file, 8, 11)).with_suggestion(
+            Span::new(file, 11, 11),
+            "add a fractional digit",
+            "0",
+        );
+        let rendered = render(&sources, &diagnostic, false);
+        assert!(rendered.contains("= help: add a fractional digit: `var x = 12.0;`\n"));
+    }
+
+    #[test]
+    fn serializes_to_a_single_json_line() {
+        let mut sources = SourceMap::new();
+        let file = sources.add("main.lox", "\"ab\n12.");
+        let diagnostic = Diagnostic::error("Bad", Span::new(file, 7, 7))
+            .with_label("ends here")
+            .with_secondary(Span::new(file, 0, 1), "starts here")
+            .with_note("a note")
+            .with_suggestion(Span::new(file, 7, 7), "add a digit", "0");
+        assert_eq!(
+            diagnostic.to_json(&sources),
+            "{\"code\":null,\"severity\":\"error\",\"message\":\"Bad\",\"file\":\"main.lox\",\
+             \"start\":{\"line\":2,\"column\":4,\"offset\":7},\
+             \"end\":{\"line\":2,\"column\":4,\"offset\":7},\"label\":\"ends here\",\
+             \"related\":[{\"message\":\"starts here\",\"file\":\"main.lox\",\
+             \"start\":{\"line\":1,\"column\":1,\"offset\":0},\
+             \"end\":{\"line\":1,\"column\":2,\"offset\":1}}],\"notes\":[\"a note\"],\
+             \"help\":null,\"fixes\":[{\"message\":\"add a digit\",\"file\":\"main.lox\",\
+             \"start\":{\"line\":2,\"column\":4,\"offset\":7},\
+             \"end\":{\"line\":2,\"column\":4,\"offset\":7},\"replacement\":\"0\"}]}"
+        );
+    }
+
+    #[test]
+    fn escapes_json_strings() {
+        assert_eq!(
+            json_string("say \"hi\"\\\n\t\u{1}"),
+            "\"say \\\"hi\\\"\\\\\\n\\t\\u0001\""
+        );
+    }
+
+    #[test]
+    fn shows_the_code_in_the_header() {
+        let mut sources = SourceMap::new();
+        let file = sources.add("main.lox", "@");
+        let diagnostic = Diagnostic::error("Bad", Span::new(file, 0, 1)).with_code("L0005");
+        assert!(render(&sources, &diagnostic, false).starts_with("error[L0005]: Bad\n"));
+        assert!(diagnostic
+            .to_json(&sources)
+            .starts_with("{\"code\":\"L0005\","));
+    }
+
+    #[test]
+    fn colors_only_when_asked() {
+        let mut sources = SourceMap::new();
+        let file = sources.add("main.lox", "@");
+        let diagnostic = Diagnostic::error("Bad", Span::new(file, 0, 1));
+        assert!(render(&sources, &diagnostic, true).starts_with("\x1b[1;31merror\x1b[0m"));
+        assert!(!render(&sources, &diagnostic, false).contains('\x1b'));
+    }
+}
@@ -0,0 +1,63 @@
+/// Long-form explanations for diagnostic codes, shown by `--explain`.
+const EXPLANATIONS: &[(&str, &str)] = &[
+    ("L0001", include_str!("explain/L0001.md")),
+    ("L0002", include_str!("explain/L0002.md")),
+    ("L0003", include_str!("explain/L0003.md")),
+    ("L0004", include_str!("explain/L0004.md")),
+    ("L0005", include_str!("explain/L0005.md")),
+    ("W0001", include_str!("explain/W0001.md")),
+    ("W0002", include_str!("explain/W0002.md")),
+    ("W0003", include_str!("explain/W0003.md")),
+    ("W0004", include_str!("explain/W0004.md")),
+    ("W0005", include_str!("explain/W0005.md")),
+    ("W0006", include_str!("explain/W0006.md")),
+    ("W0007", include_str!("explain/W0007.md")),
+    ("W0008", include_str!("explain/W0008.md")),
+];
+
+pub fn explain(code: &str) -> Option<&'static str> {
+    EXPLANATIONS
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(code))
+        .map(|(_, explanation)| *explanation)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{explain::explain, lint::LINTS, scanner::ScanErrorKind};
+
+    #[test]
+    fn every_scan_error_has_an_explanation() {
+        let kinds = [
+            ScanErrorKind::UnterminatedString,
+            ScanErrorKind::InvalidFloat,
+            ScanErrorKind::FloatEndsWithDot,
+            ScanErrorKind::InvalidInteger,
+            ScanErrorKind::UnexpectedCharacter('@'),
+        ];
+        for kind in kinds {
+            assert!(
+                explain(kind.code()).is_some(),
+                "{} is undocumented",
+                kind.code()
+            );
+        }
+    }
+
+    #[test]
+    fn every_lint_has_an_explanation() {
+        for lint in LINTS {
+            assert!(
+                explain(lint.code()).is_some(),
+                "{} is undocumented",
+                lint.code()
+            );
+        }
+    }
+
+    #[test]
+    fn looks_up_codes_case_insensitively() {
+        assert_eq!(explain("l0001"), explain("L0001"));
+        assert!(explain("X9999").is_none());
+    }
+}
@@ -0,0 +1,687 @@
+use crate::{scanner::KEYWORDS, streams::Streams};
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, BufRead, Write},
+    path::PathBuf,
+};
+use terminal::RawMode;
+
+/// A key press, decoded from the bytes a terminal sends in raw mode.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Key {
+    Char(char),
+    Ctrl(char),
+    Enter,
+    Tab,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    /// An escape sequence the editor does not handle.
+    Unknown,
+}
+
+fn read_byte(input: &mut dyn BufRead) -> io::Result<Option<u8>> {
+    let byte = input.fill_buf()?.first().copied();
+    if byte.is_some() {
+        input.consume(1);
+    }
+    Ok(byte)
+}
+
+/// Reads one key, or `None` at end of input. `raw` is the terminal's raw
+/// mode when `input` is a terminal, used to tell Esc from the start of an
+/// escape sequence.
+fn read_key(input: &mut dyn BufRead, raw: Option<&RawMode>) -> io::Result<Option<Key>> {
+    let Some(byte) = read_byte(input)? else {
+        return Ok(None);
+    };
+    let key = match byte {
+        b'\r' | b'\n' => Key::Enter,
+        b'\t' => Key::Tab,
+        0x7f | 0x08 => Key::Backspace,
+        0x1b => read_escape(input, raw)?,
+        0x01..=0x1a => Key::Ctrl((b'a' + byte - 1) as char),
+        0x80.. => {
+            let len = match byte {
+                0xf0.. => 4,
+                0xe0.. => 3,
+                _ => 2,
+            };
+            let mut bytes = vec![byte];
+            for _ in 1..len {
+                bytes.extend(read_byte(input)?);
+            }
+            match std::str::from_utf8(&bytes) {
+                Ok(text) => Key::Char(text.chars().next().unwrap()),
+                Err(_) => Key::Unknown,
+            }
+        }
+        _ if byte.is_ascii_control() => Key::Unknown,
+        _ => Key::Char(byte as char),
+    };
+    Ok(Some(key))
+}
+
+/// Reads the rest of an escape sequence. A terminal sends a whole sequence
+/// at once, so the bytes after ESC are read with a short timeout; if none
+/// arrive, Esc was pressed on its own and nothing more is consumed.
+fn read_escape(input: &mut dyn BufRead, raw: Option<&RawMode>) -> io::Result<Key> {
+    if let Some(raw) = raw {
+        raw.set_timeout(true);
+    }
+    let key = read_sequence(input);
+    if let Some(raw) = raw {
+        raw.set_timeout(false);
+    }
+    key
+}
+
+fn read_sequence(input: &mut dyn BufRead) -> io::Result<Key> {
+    // Peek first, so a key pressed after a lone Esc is not swallowed.
+    let Some(&kind @ (b'[' | b'O')) = input.fill_buf()?.first() else {
+        return Ok(Key::Unknown);
+    };
+    input.consume(1);
+
+    // `ESC [` is followed by parameter bytes, such as the `1;5` in Ctrl-Right's
+    // `ESC [ 1 ; 5 C`, and then a final byte naming the key. Modifiers are
+    // ignored, so Ctrl-Right moves like Right.
+    let mut params = Vec::new();
+    let final_byte = loop {
+        match read_byte(input)? {
+            Some(byte @ 0x30..=0x3f) if kind == b'[' => params.push(byte),
+            Some(byte @ 0x40..=0x7e) => break byte,
+            _ => return Ok(Key::Unknown),
+        }
+    };
+    let code = params
+        .split(|&byte| byte == b';')
+        .next()
+        .unwrap_or_default();
+    let key = match final_byte {
+        b'A' => Key::Up,
+        b'B' => Key::Down,
+        b'C' => Key::Right,
+        b'D' => Key::Left,
+        b'H' => Key::Home,
+        b'F' => Key::End,
+        b'~' => match code {
+            b"1" | b"7" => Key::Home,
+            b"3" => Key::Delete,
+            b"4" | b"8" => Key::End,
+            _ => Key::Unknown,
+        },
+        _ => Key::Unknown,
+    };
+    Ok(key)
+}
+
+/// The line being edited and the cursor position within it, in characters.
+#[derive(Debug, Default)]
+struct Line {
+    chars: Vec<char>,
+    cursor: usize,
+}
+
+impl Line {
+    fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+
+    fn set(&mut self, text: &str) {
+        self.chars = text.chars().collect();
+        self.cursor = self.chars.len();
+    }
+
+    fn insert(&mut self, c: char) {
+        self.chars.insert(self.cursor, c);
+        self.cursor += 1;
+    }
+
+    fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.chars.remove(self.cursor);
+        }
+    }
+
+    fn delete(&mut self) {
+        if self.cursor < self.chars.len() {
+            self.chars.remove(self.cursor);
+        }
+    }
+
+    fn kill_to_end(&mut self) {
+        self.chars.truncate(self.cursor);
+    }
+
+    fn kill_to_start(&mut self) {
+        self.chars.drain(..self.cursor);
+        self.cursor = 0;
+    }
+
+    /// Deletes the word before the cursor, along with any spaces after it.
+    fn kill_word(&mut self) {
+        let mut start = self.cursor;
+        while start > 0 && self.chars[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        while start > 0 && !self.chars[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        self.chars.drain(start..self.cursor);
+        self.cursor = start;
+    }
+
+    /// Completes the identifier before the cursor from `words`. Inserts as
+    /// much as all candidates share and returns the candidates when there
+    /// is more than one.
+    fn complete<'w>(&mut self, words: &[&'w str]) -> Vec<&'w str> {
+        let mut start = self.cursor;
+        while start > 0 && self.chars[start - 1].is_ascii_alphanumeric() {
+            start -= 1;
+        }
+        if start == self.cursor {
+            return Vec::new();
+        }
+
+        let prefix: String = self.chars[start..self.cursor].iter().collect();
+        let candidates: Vec<&str> = words
+            .iter()
+            .copied()
+            .filter(|word| word.starts_with(&prefix))
+            .collect();
+        let Some(first) = candidates.first() else {
+            return candidates;
+        };
+        let shared = candidates.iter().fold(first.len(), |len, word| {
+            first
+                .bytes()
+                .zip(word.bytes())
+                .take(len)
+                .take_while(|(a, b)| a == b)
+                .count()
+        });
+        for c in first[prefix.len()..shared].chars() {
+            self.insert(c);
+        }
+        if candidates.len() == 1 {
+            Vec::new()
+        } else {
+            candidates
+        }
+    }
+}
+
+/// Previously entered lines, oldest first, mirrored to a file if one is set.
+#[derive(Debug, Default)]
+pub struct History {
+    entries: Vec<String>,
+    path: Option<PathBuf>,
+}
+
+impl History {
+    /// `~/.rustylox_history`, if there is a home directory.
+    pub fn default_path() -> Option<PathBuf> {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".rustylox_history"))
+    }
+
+    /// Loads the history stored at `path`. A missing or unreadable file is
+    /// treated as empty; history is a convenience, not something to fail on.
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let entries = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|contents| contents.lines().map(str::to_string).collect())
+            .unwrap_or_default();
+        History { entries, path }
+    }
+
+    /// Records `entry` unless it is blank or repeats the previous entry.
+    fn add(&mut self, entry: &str) {
+        if entry.trim().is_empty() || self.entries.last().map(String::as_str) == Some(entry) {
+            return;
+        }
+        self.entries.push(entry.to_string());
+        if let Some(path) = &self.path {
+            let file = OpenOptions::new().create(true).append(true).open(path);
+            if let Ok(mut file) = file {
+                let _ = writeln!(file, "{}", entry);
+            }
+        }
+    }
+
+    /// The index of the newest entry before `before` that contains `query`.
+    fn search(&self, query: &str, before: usize) -> Option<usize> {
+        self.entries[..before]
+            .iter()
+            .rposition(|entry| entry.contains(query))
+    }
+}
+
+/// An in-progress reverse history search.
+struct Search {
+    query: String,
+    found: Option<usize>,
+}
+
+/// Reads prompt lines with editing, history and keyword completion when
+/// the input is a terminal, and plain lines otherwise.
+pub struct LineEditor {
+    history: History,
+}
+
+impl LineEditor {
+    pub fn new(history: History) -> Self {
+        LineEditor { history }
+    }
+
+    /// Reads one line without its newline, or `None` at end of input. A
+    /// Ctrl-C returns an `Interrupted` error so the caller can drop any
+    /// pending input.
+    pub fn read_line(&mut self, prompt: &str, streams: &mut Streams) -> io::Result<Option<String>> {
+        let raw_mode = if streams.interactive {
+            RawMode::enable()
+        } else {
+            None
+        };
+        let Some(raw_mode) = raw_mode else {
+            write!(streams.out, "{}", prompt)?;
+            streams.out.flush()?;
+            let mut line = String::new();
+            return Ok(match streams.input.read_line(&mut line)? {
+                0 => None,
+                _ => Some(line.trim_end_matches(['\n', '\r']).to_string()),
+            });
+        };
+
+        let result = self.edit(prompt, streams, &raw_mode);
+        drop(raw_mode);
+        write!(streams.out, "\r\n")?;
+        streams.out.flush()?;
+        if let Ok(Some(line)) = &result {
+            self.history.add(line);
+        }
+        result
+    }
+
+    fn edit(
+        &mut self,
+        prompt: &str,
+        streams: &mut Streams,
+        raw: &RawMode,
+    ) -> io::Result<Option<String>> {
+        let mut line = Line::default();
+        let mut position = self.history.entries.len();
+        let mut draft = String::new();
+        let mut search: Option<Search> = None;
+
+        loop {
+            match &search {
+                Some(search) => {
+                    let found = search.found.map_or("", |idx| &self.history.entries[idx]);
+                    let status = format!("(reverse-i-search)`{}': ", search.query);
+                    refresh(streams, &status, found, found.chars().count())?;
+                }
+                None => refresh(streams, prompt, &line.text(), line.cursor)?,
+            }
+
+            let Some(key) = read_key(&mut streams.input, Some(raw))? else {
+                return Ok(None);
+            };
+
+            if let Some(current) = &mut search {
+                match key {
+                    Key::Char(c) => {
+                        current.query.push(c);
+                        current.found = self
+                            .history
+                            .search(&current.query, self.history.entries.len());
+                    }
+                    Key::Backspace => {
+                        current.query.pop();
+                        current.found = self
+                            .history
+                            .search(&current.query, self.history.entries.len());
+                    }
+                    Key::Ctrl('r') => {
+                        let before = current.found.unwrap_or(self.history.entries.len());
+                        if let Some(idx) = self.history.search(&current.query, before) {
+                            current.found = Some(idx);
+                        }
+                    }
+                    Key::Ctrl('c' | 'g') => search = None,
+                    _ => {
+                        if let Some(idx) = current.found {
+                            line.set(&self.history.entries[idx]);
+                        }
+                        search = None;
+                        if key == Key::Enter {
+                            return Ok(Some(line.text()));
+                        }
+                    }
+                }
+                continue;
+            }
+
+            match key {
+                Key::Char(c) => line.insert(c),
+                Key::Enter => return Ok(Some(line.text())),
+                Key::Tab => {
+                    let candidates = line.complete(&KEYWORDS);
+                    if !candidates.is_empty() {
+                        write!(streams.out, "\r\n{}\r\n", candidates.join("  "))?;
+                    }
+                }
+                Key::Backspace => line.backspace(),
+                Key::Delete => line.delete(),
+                Key::Left | Key::Ctrl('b') => line.cursor = line.cursor.saturating_sub(1),
+                Key::Right | Key::Ctrl('f') => {
+                    line.cursor = (line.cursor + 1).min(line.chars.len())
+                }
+                Key::Home | Key::Ctrl('a') => line.cursor = 0,
+                Key::End | Key::Ctrl('e') => line.cursor = line.chars.len(),
+                Key::Ctrl('k') => line.kill_to_end(),
+                Key::Ctrl('u') => line.kill_to_start(),
+                Key::Ctrl('w') => line.kill_word(),
+                Key::Ctrl('r') => {
+                    search = Some(Search {
+                        query: String::new(),
+                        found: None,
+                    })
+                }
+                Key::Up if position > 0 => {
+                    if position == self.history.entries.len() {
+                        draft = line.text();
+                    }
+                    position -= 1;
+                    line.set(&self.history.entries[position]);
+                }
+                Key::Down if position < self.history.entries.len() => {
+                    position += 1;
+                    match self.history.entries.get(position) {
+                        Some(entry) => line.set(entry),
+                        None => line.set(&draft),
+                    }
+                }
+                Key::Ctrl('d') if line.chars.is_empty() => return Ok(None),
+                Key::Ctrl('d') => line.delete(),
+                Key::Ctrl('c') => {
+                    write!(streams.out, "^C")?;
+                    return Err(io::ErrorKind::Interrupted.into());
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Redraws the current line and puts the cursor `cursor` characters past
+/// the prompt.
+fn refresh(streams: &mut Streams, prompt: &str, text: &str, cursor: usize) -> io::Result<()> {
+    write!(streams.out, "\r{}{}\x1b[K\r", prompt, text)?;
+    let column = prompt.chars().count() + cursor;
+    if column > 0 {
+        write!(streams.out, "\x1b[{}C", column)?;
+    }
+    streams.out.flush()
+}
+
+/// Raw mode for the process's standard input, restored when dropped. Only
+/// the termios layout and flag values shared by Linux on x86, Arm and
+/// RISC-V are described here; PowerPC, MIPS and SPARC use different ones,
+/// so there and on other systems the editor falls back to plain lines.
+#[cfg(all(
+    target_os = "linux",
+    any(
+        target_arch = "x86_64",
+        target_arch = "x86",
+        target_arch = "aarch64",
+        target_arch = "arm",
+        target_arch = "riscv64"
+    )
+))]
+mod terminal {
+    const ICRNL: u32 = 0o400;
+    const IXON: u32 = 0o2000;
+    const ISIG: u32 = 0o1;
+    const ICANON: u32 = 0o2;
+    const ECHO: u32 = 0o10;
+    const IEXTEN: u32 = 0o100000;
+    const VTIME: usize = 5;
+    const VMIN: usize = 6;
+    const TCSANOW: i32 = 0;
+    const STDIN: i32 = 0;
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct Termios {
+        iflag: u32,
+        oflag: u32,
+        cflag: u32,
+        lflag: u32,
+        line: u8,
+        cc: [u8; 32],
+        ispeed: u32,
+        ospeed: u32,
+    }
+
+    extern "C" {
+        fn tcgetattr(fd: i32, termios: *mut Termios) -> i32;
+        fn tcsetattr(fd: i32, action: i32, termios: *const Termios) -> i32;
+    }
+
+    pub struct RawMode {
+        original: Termios,
+        raw: Termios,
+    }
+
+    impl RawMode {
+        pub fn enable() -> Option<RawMode> {
+            let mut original = std::mem::MaybeUninit::<Termios>::uninit();
+            // SAFETY: tcgetattr fills in the struct when it returns 0, and
+            // `Termios` matches the C library's layout on these targets.
+            let original = unsafe {
+                if tcgetattr(STDIN, original.as_mut_ptr()) != 0 {
+                    return None;
+                }
+                original.assume_init()
+            };
+
+            let mut raw = original;
+            raw.iflag &= !(ICRNL | IXON);
+            raw.lflag &= !(ECHO | ICANON | IEXTEN | ISIG);
+            raw.cc[VMIN] = 1;
+            raw.cc[VTIME] = 0;
+            // SAFETY: `raw` is a valid termios read back from the terminal.
+            // TCSANOW keeps anything typed or pasted ahead of the prompt.
+            if unsafe { tcsetattr(STDIN, TCSANOW, &raw) } != 0 {
+                return None;
+            }
+            Some(RawMode { original, raw })
+        }
+
+        /// Switches reads between blocking for a byte and giving up after a
+        /// tenth of a second.
+        pub fn set_timeout(&self, timeout: bool) {
+            let mut termios = self.raw;
+            if timeout {
+                termios.cc[VMIN] = 0;
+                termios.cc[VTIME] = 1;
+            }
+            // SAFETY: `termios` is a valid termios read back from the
+            // terminal. TCSANOW keeps input that has already arrived.
+            unsafe {
+                tcsetattr(STDIN, TCSANOW, &termios);
+            }
+        }
+    }
+
+    impl Drop for RawMode {
+        fn drop(&mut self) {
+            // SAFETY: restores the settings read in `enable`, keeping any
+            // input that arrived after the line was submitted.
+            unsafe {
+                tcsetattr(STDIN, TCSANOW, &self.original);
+            }
+        }
+    }
+}
+
+#[cfg(not(all(
+    target_os = "linux",
+    any(
+        target_arch = "x86_64",
+        target_arch = "x86",
+        target_arch = "aarch64",
+        target_arch = "arm",
+        target_arch = "riscv64"
+    )
+)))]
+mod terminal {
+    pub enum RawMode {}
+
+    impl RawMode {
+        pub fn enable() -> Option<RawMode> {
+            None
+        }
+
+        pub fn set_timeout(&self, _timeout: bool) {
+            match *self {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::line_editor::{read_key, History, Key, Line};
+
+    fn keys(mut bytes: &[u8]) -> Vec<Key> {
+        let mut keys = Vec::new();
+        while let Some(key) = read_key(&mut bytes, None).unwrap() {
+            keys.push(key);
+        }
+        keys
+    }
+
+    fn line(text: &str, cursor: usize) -> Line {
+        Line {
+            chars: text.chars().collect(),
+            cursor,
+        }
+    }
+
+    #[test]
+    fn decodes_keys_and_escape_sequences() {
+        assert_eq!(
+            keys(
+                b"a\x01\r\t\x7f\x1b[A\x1b[D\x1bOH\x1b[3~\x1b[4~\x1b[9~\x1b[1;5C\x1b[1;2D\x1b[3;5~"
+            ),
+            vec![
+                Key::Char('a'),
+                Key::Ctrl('a'),
+                Key::Enter,
+                Key::Tab,
+                Key::Backspace,
+                Key::Up,
+                Key::Left,
+                Key::Home,
+                Key::Delete,
+                Key::End,
+                Key::Unknown,
+                Key::Right,
+                Key::Left,
+                Key::Delete,
+            ]
+        );
+        assert_eq!(keys("é".as_bytes()), vec![Key::Char('é')]);
+        assert_eq!(keys(b"\x1b"), vec![Key::Unknown]);
+        assert_eq!(keys(b"\x1b\x1b[B"), vec![Key::Unknown, Key::Down]);
+    }
+
+    #[test]
+    fn edits_around_the_cursor() {
+        let mut edited = line("print 12;", 8);
+        edited.insert('3');
+        assert_eq!(edited.text(), "print 123;");
+        edited.backspace();
+        edited.delete();
+        assert_eq!(edited.text(), "print 12");
+
+        let mut edited = line("var a = 1;", 5);
+        edited.kill_to_end();
+        assert_eq!(edited.text(), "var a");
+
+        let mut edited = line("var a = 1;", 6);
+        edited.kill_to_start();
+        assert_eq!((edited.text().as_str(), edited.cursor), ("= 1;", 0));
+    }
+
+    #[test]
+    fn kills_the_previous_word() {
+        let mut edited = line("print foo  bar", 11);
+        edited.kill_word();
+        assert_eq!(edited.text(), "print bar");
+        assert_eq!(edited.cursor, 6);
+    }
+
+    #[test]
+    fn completes_keywords() {
+        let words = ["class", "const", "continue", "while"];
+
+        let mut edited = line("wh", 2);
+        assert!(edited.complete(&words).is_empty());
+        assert_eq!(edited.text(), "while");
+
+        let mut edited = line("con", 3);
+        assert_eq!(edited.complete(&words), vec!["const", "continue"]);
+        assert_eq!(edited.text(), "con");
+
+        let mut edited = line("c", 1);
+        assert_eq!(edited.complete(&words).len(), 3);
+
+        let mut edited = line("print ", 6);
+        assert!(edited.complete(&words).is_empty());
+        assert_eq!(edited.text(), "print ");
+    }
+
+    #[test]
+    fn history_skips_blanks_and_repeats() {
+        let mut history = History::default();
+        history.add("print 1;");
+        history.add("print 1;");
+        history.add("  ");
+        history.add("var a = 1;");
+        assert_eq!(history.entries, vec!["print 1;", "var a = 1;"]);
+    }
+
+    #[test]
+    fn history_searches_backwards() {
+        let mut history = History::default();
+        history.add("print 1;");
+        history.add("var a = 1;");
+        history.add("print a;");
+        assert_eq!(history.search("print", 3), Some(2));
+        assert_eq!(history.search("print", 2), Some(0));
+        assert_eq!(history.search("nothing", 3), None);
+    }
+
+    #[test]
+    fn history_persists_to_its_file() {
+        let path = std::env::temp_dir().join(format!("rustylox_history_{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut history = History::load(Some(path.clone()));
+        history.add("print 1;");
+        history.add("print 2;");
+        let reloaded = History::load(Some(path.clone()));
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(reloaded.entries, vec!["print 1;", "print 2;"]);
+    }
+}
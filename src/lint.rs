@@ -0,0 +1,450 @@
+use crate::{
+    diagnostic::{Diagnostic, Severity},
+    scanner::scan_pragmas,
+    source_map::{FileId, SourceMap, Span},
+    token::Token,
+};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lint {
+    UnusedVariables,
+    UnusedParameters,
+    UnreachableCode,
+    Shadowing,
+    AssignmentInCondition,
+    NilComparison,
+    EmptyBlock,
+    UnknownLints,
+}
+
+pub const LINTS: [Lint; 8] = [
+    Lint::UnusedVariables,
+    Lint::UnusedParameters,
+    Lint::UnreachableCode,
+    Lint::Shadowing,
+    Lint::AssignmentInCondition,
+    Lint::NilComparison,
+    Lint::EmptyBlock,
+    Lint::UnknownLints,
+];
+
+impl Lint {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Lint::UnusedVariables => "unused_variables",
+            Lint::UnusedParameters => "unused_parameters",
+            Lint::UnreachableCode => "unreachable_code",
+            Lint::Shadowing => "shadowing",
+            Lint::AssignmentInCondition => "assignment_in_condition",
+            Lint::NilComparison => "nil_comparison",
+            Lint::EmptyBlock => "empty_block",
+            Lint::UnknownLints => "unknown_lints",
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Lint::UnusedVariables => "W0001",
+            Lint::UnusedParameters => "W0002",
+            Lint::UnreachableCode => "W0003",
+            Lint::Shadowing => "W0004",
+            Lint::AssignmentInCondition => "W0005",
+            Lint::NilComparison => "W0006",
+            Lint::EmptyBlock => "W0007",
+            Lint::UnknownLints => "W0008",
+        }
+    }
+
+    /// Whether the lint runs yet. The others need the resolver to tell
+    /// scopes and control flow apart, and can only be allowed until then.
+    fn is_implemented(&self) -> bool {
+        matches!(
+            self,
+            Lint::AssignmentInCondition | Lint::EmptyBlock | Lint::UnknownLints
+        )
+    }
+
+    /// The level a lint has unless overridden: warn if it runs, allow if
+    /// it does not yet.
+    pub fn default_level(&self) -> Level {
+        if self.is_implemented() {
+            Level::Warn
+        } else {
+            Level::Allow
+        }
+    }
+
+    /// Resolves a lint or group name, as used on the command line and in
+    /// `// lox-allow(...)` pragmas.
+    fn named(name: &str) -> Option<Vec<Lint>> {
+        match name {
+            "unused" => Some(vec![Lint::UnusedVariables, Lint::UnusedParameters]),
+            _ => LINTS
+                .iter()
+                .find(|lint| lint.name() == name)
+                .map(|lint| vec![*lint]),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Level::Allow => write!(f, "allow"),
+            Level::Warn => write!(f, "warn"),
+            Level::Deny => write!(f, "deny"),
+        }
+    }
+}
+
+/// Per-lint levels. Each lint has its default level unless overridden;
+/// later overrides win, so `-A unused -W unused_parameters` behaves as
+/// expected.
+#[derive(Default)]
+pub struct LintConfig {
+    overrides: Vec<(Lint, Level)>,
+}
+
+impl LintConfig {
+    /// Overrides the level of a lint or group. Raising a lint that does not
+    /// run yet is an error, so `-D` never promises a check that is missing.
+    pub fn set(&mut self, name: &str, level: Level) -> Result<(), String> {
+        let lints = Lint::named(name).ok_or_else(|| format!("Unknown lint '{}'", name))?;
+        if level != Level::Allow {
+            if let Some(lint) = lints.iter().find(|lint| !lint.is_implemented()) {
+                return Err(format!("Lint '{}' is not implemented yet", lint.name()));
+            }
+        }
+        self.overrides
+            .extend(lints.into_iter().map(|lint| (lint, level)));
+        Ok(())
+    }
+
+    /// The severity `lint` is reported with, or `None` if it is allowed.
+    fn severity(&self, lint: Lint) -> Option<Severity> {
+        match self.level(lint) {
+            Level::Allow => None,
+            Level::Warn => Some(Severity::Warning),
+            Level::Deny => Some(Severity::Error),
+        }
+    }
+
+    pub fn level(&self, lint: Lint) -> Level {
+        self.overrides
+            .iter()
+            .rev()
+            .find(|(overridden, _)| *overridden == lint)
+            .map_or(lint.default_level(), |(_, level)| *level)
+    }
+}
+
+/// Runs every lint over the tokens of `file` and returns the diagnostics
+/// that are neither allowed by `config` nor silenced by a pragma on the
+/// same or the previous line.
+///
+/// There is no syntax tree yet, so only the lints that can be judged from
+/// the token stream run here.
+pub fn check(
+    sources: &SourceMap,
+    file: FileId,
+    tokens: &[Token],
+    config: &LintConfig,
+) -> Vec<Diagnostic> {
+    let source = sources.get(file);
+    let pragmas = scan_pragmas(source.source(), tokens);
+    let linter = Linter::new(source.source(), tokens, config);
+
+    let mut findings = Vec::new();
+    linter.assignment_in_condition(&mut findings);
+    linter.empty_block(&mut findings);
+    if let Some(severity) = config.severity(Lint::UnknownLints) {
+        for pragma in &pragmas {
+            for (name, span) in &pragma.lints {
+                if Lint::named(name).is_none() {
+                    findings.push((
+                        Lint::UnknownLints,
+                        Diagnostic::new(severity, format!("unknown lint: `{}`", name), *span)
+                            .with_label("not a known lint or lint group"),
+                    ));
+                }
+            }
+        }
+    }
+
+    let allowed = |lint: Lint, span: Span| {
+        let line = source.location(span.start).line;
+        pragmas.iter().any(|pragma| {
+            let pragma_line = source.location(pragma.span.start).line;
+            (pragma_line == line || pragma_line + 1 == line)
+                && pragma
+                    .lints
+                    .iter()
+                    .any(|(name, _)| Lint::named(name).is_some_and(|lints| lints.contains(&lint)))
+        })
+    };
+
+    findings.sort_by_key(|(_, diagnostic)| diagnostic.primary.span.start);
+    findings
+        .into_iter()
+        .filter(|(lint, diagnostic)| !allowed(*lint, diagnostic.primary.span))
+        .map(|(lint, diagnostic)| {
+            diagnostic.with_code(lint.code()).with_note(format!(
+                "`{}` is set to {}",
+                lint.name(),
+                config.level(lint)
+            ))
+        })
+        .collect()
+}
+
+type Findings = Vec<(Lint, Diagnostic)>;
+
+struct Linter<'s, 't> {
+    source: &'s str,
+    tokens: &'t [Token<'s>],
+    config: &'t LintConfig,
+    /// For each bracket token, the index of its partner, if balanced.
+    partners: Vec<Option<usize>>,
+}
+
+impl<'s, 't> Linter<'s, 't> {
+    fn new(source: &'s str, tokens: &'t [Token<'s>], config: &'t LintConfig) -> Self {
+        let mut partners = vec![None; tokens.len()];
+        let mut open: Vec<usize> = Vec::new();
+        for (idx, token) in tokens.iter().enumerate() {
+            if is_open(token) {
+                open.push(idx);
+            } else if is_close(token) {
+                if let Some(start) = open.pop() {
+                    if closes(&tokens[start], token) {
+                        partners[start] = Some(idx);
+                        partners[idx] = Some(start);
+                    }
+                }
+            }
+        }
+        Linter {
+            source,
+            tokens,
+            config,
+            partners,
+        }
+    }
+
+    /// Index of the token after the one at `idx`, jumping over a whole
+    /// bracketed group if `idx` opens one.
+    fn skip(&self, idx: usize) -> usize {
+        if is_open(&self.tokens[idx]) {
+            self.partners[idx].map_or(self.tokens.len(), |close| close + 1)
+        } else {
+            idx + 1
+        }
+    }
+
+    /// Whether the token at `idx` sits where a statement may begin.
+    fn at_statement_start(&self, idx: usize) -> bool {
+        idx == 0
+            || matches!(
+                self.tokens[idx - 1],
+                Token::LeftBrace { .. } | Token::RightBrace { .. } | Token::Semicolon { .. }
+            )
+    }
+
+    /// `=` directly inside the parentheses of an `if` or `while`. Doubling
+    /// the parentheses marks the assignment as intended.
+    fn assignment_in_condition(&self, findings: &mut Findings) {
+        let Some(severity) = self.config.severity(Lint::AssignmentInCondition) else {
+            return;
+        };
+        for (idx, token) in self.tokens.iter().enumerate() {
+            if !matches!(token, Token::If { .. } | Token::While { .. })
+                || !matches!(self.tokens.get(idx + 1), Some(Token::LeftParen { .. }))
+            {
+                continue;
+            }
+            let Some(close) = self.partners[idx + 1] else {
+                continue;
+            };
+
+            let mut inner = idx + 2;
+            while inner < close {
+                if let Token::Equal { span, .. } = &self.tokens[inner] {
+                    findings.push((
+                        Lint::AssignmentInCondition,
+                        Diagnostic::new(
+                            severity,
+                            format!("assignment used as the condition of `{}`", token.lexeme()),
+                            *span,
+                        )
+                        .with_label("this assigns rather than compares")
+                        .with_suggestion(*span, "compare with `==` instead", "==")
+                        .with_help(
+                            "wrap the assignment in a second pair of parentheses if it is intended",
+                        ),
+                    ));
+                }
+                inner = self.skip(inner);
+            }
+        }
+    }
+
+    /// `{}` used as a statement or as the body of a control-flow construct.
+    /// Function and class bodies and map literals are left alone, as are
+    /// blocks containing a comment explaining why they are empty.
+    fn empty_block(&self, findings: &mut Findings) {
+        let Some(severity) = self.config.severity(Lint::EmptyBlock) else {
+            return;
+        };
+        for (idx, token) in self.tokens.iter().enumerate() {
+            if !matches!(token, Token::LeftBrace { .. }) || self.partners[idx] != Some(idx + 1) {
+                continue;
+            }
+            let close = &self.tokens[idx + 1];
+            if !self.source[token.span().end..close.span().start]
+                .trim()
+                .is_empty()
+            {
+                continue;
+            }
+
+            let is_block = self.at_statement_start(idx)
+                || match &self.tokens[idx - 1] {
+                    Token::Else { .. } | Token::Try { .. } | Token::Finally { .. } => true,
+                    Token::RightParen { .. } => self.partners[idx - 1]
+                        .and_then(|open| open.checked_sub(1))
+                        .is_some_and(|keyword| {
+                            matches!(
+                                self.tokens[keyword],
+                                Token::If { .. }
+                                    | Token::While { .. }
+                                    | Token::For { .. }
+                                    | Token::Catch { .. }
+                            )
+                        }),
+                    _ => false,
+                };
+            if is_block {
+                let span = Span::new(token.span().file, token.span().start, close.span().end);
+                findings.push((
+                    Lint::EmptyBlock,
+                    Diagnostic::new(severity, "empty block", span)
+                        .with_label("this block has no statements")
+                        .with_help("remove it, or add a comment saying why it is empty"),
+                ));
+            }
+        }
+    }
+}
+
+fn is_open(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::LeftParen { .. } | Token::LeftBracket { .. } | Token::LeftBrace { .. }
+    )
+}
+
+fn is_close(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::RightParen { .. } | Token::RightBracket { .. } | Token::RightBrace { .. }
+    )
+}
+
+fn closes(open: &Token, close: &Token) -> bool {
+    matches!(
+        (open, close),
+        (Token::LeftParen { .. }, Token::RightParen { .. })
+            | (Token::LeftBracket { .. }, Token::RightBracket { .. })
+            | (Token::LeftBrace { .. }, Token::RightBrace { .. })
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        diagnostic::Severity,
+        lint::{check, Level, Lint, LintConfig},
+        scanner::scan_tokens,
+        source_map::SourceMap,
+    };
+
+    fn lint_with(source: &str, config: &LintConfig) -> Vec<(Option<&'static str>, Severity)> {
+        let mut sources = SourceMap::new();
+        let file = sources.add("test.lox", source);
+        let tokens = scan_tokens(file, sources.get(file).source()).unwrap();
+        check(&sources, file, &tokens, config)
+            .into_iter()
+            .map(|diagnostic| (diagnostic.code, diagnostic.severity))
+            .collect()
+    }
+
+    fn codes(source: &str) -> Vec<&'static str> {
+        lint_with(source, &LintConfig::default())
+            .into_iter()
+            .filter_map(|(code, _)| code)
+            .collect()
+    }
+
+    #[test]
+    fn reports_empty_blocks_but_not_empty_bodies() {
+        assert_eq!(codes("if (true) {} else {}"), ["W0007", "W0007"]);
+        assert_eq!(codes("{}"), ["W0007"]);
+        assert!(codes("fun f() {} class A {} var m = {};").is_empty());
+        assert!(codes("if (true) { // nothing to do\n}").is_empty());
+    }
+
+    #[test]
+    fn reports_assignments_used_as_conditions() {
+        assert_eq!(codes("var a; if (a = 1) print a;"), ["W0005"]);
+        assert_eq!(codes("var a; while (a = 1) print a;"), ["W0005"]);
+        assert!(codes("var a; if ((a = 1)) print a;").is_empty());
+        assert!(codes("var a; if (a == 1) print a;").is_empty());
+        assert!(codes("var a = 1; if (a) { a = 2; }").is_empty());
+    }
+
+    #[test]
+    fn pragmas_silence_the_same_and_next_line() {
+        assert!(codes("// lox-allow(empty_block)\n{}").is_empty());
+        assert!(codes("{} // lox-allow(empty_block)").is_empty());
+        assert_eq!(codes("// lox-allow(empty_block)\n\n{}"), ["W0007"]);
+    }
+
+    #[test]
+    fn reports_unknown_lints_in_pragmas() {
+        assert_eq!(codes("// lox-allow(unused_variable)\n"), ["W0008"]);
+    }
+
+    #[test]
+    fn levels_can_be_overridden() {
+        assert_eq!(
+            lint_with("{}", &LintConfig::default()),
+            [(Some("W0007"), Severity::Warning)]
+        );
+
+        let mut config = LintConfig::default();
+        config.set("empty_block", Level::Allow).unwrap();
+        config.set("empty_block", Level::Deny).unwrap();
+        assert_eq!(lint_with("{}", &config), [(Some("W0007"), Severity::Error)]);
+        assert!(config.set("unused_variable", Level::Warn).is_err());
+    }
+
+    #[test]
+    fn unimplemented_lints_can_only_be_allowed() {
+        let mut config = LintConfig::default();
+        assert_eq!(config.level(Lint::UnusedVariables), Level::Allow);
+        assert_eq!(
+            config.set("unused", Level::Deny),
+            Err("Lint 'unused_variables' is not implemented yet".to_string())
+        );
+        assert!(config.set("shadowing", Level::Warn).is_err());
+        assert!(config.set("unused", Level::Allow).is_ok());
+    }
+}
@@ -1,60 +1,476 @@
+mod cli;
+mod diagnostic;
+mod explain;
+mod line_editor;
+mod lint;
 mod scanner;
+mod source_map;
+mod streams;
 mod token;
-use std::io::{self, Write};
+use cli::{Command, TokenFormat};
+use diagnostic::{json_string, Severity};
+use line_editor::{History, LineEditor};
+use lint::LintConfig;
+use source_map::{FileId, SourceMap};
+use std::io::{self, Read, Write};
+use streams::Streams;
+use token::Token;
 
 fn main() {
-    let args: Vec<String> = std::env::args().collect();
-    match args.len() {
-        1 => run_prompt(),
-        2 => run_file(&args[1]),
-        _ => {
-            eprintln!("Usage: rustylox [script]");
+    let mut streams = Streams::stdio();
+    let options = match cli::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            writeln!(streams.err, "{}\n\n{}", message, cli::USAGE).unwrap();
             std::process::exit(64);
         }
+    };
+    streams.error_format = options.error_format;
+    let lints = &options.lints;
+
+    match options.command {
+        Command::Repl if streams.interactive => run_prompt(&mut streams, lints),
+        Command::Repl => run_file("-", &mut streams, lints),
+        Command::Run { path } => run_file(&path, &mut streams, lints),
+        Command::Eval { source } => {
+            let mut sources = SourceMap::new();
+            let file = sources.add("<eval>", source);
+            if !run(&sources, file, &mut streams, lints) {
+                std::process::exit(1);
+            }
+        }
+        Command::Tokens { path, format } => {
+            let (sources, file) = load(&path, &mut streams);
+            match scan(&sources, file, &mut streams, lints) {
+                Some(tokens) => write_tokens(&sources, &tokens, format, &mut streams),
+                None => std::process::exit(1),
+            }
+        }
+        Command::Check { path } => {
+            let (sources, file) = load(&path, &mut streams);
+            if scan(&sources, file, &mut streams, lints).is_none() {
+                std::process::exit(1);
+            }
+        }
+        Command::Explain { code } => match explain::explain(&code) {
+            Some(explanation) => write!(streams.out, "{}", explanation).unwrap(),
+            None => {
+                writeln!(streams.err, "Unknown error code '{}'", code).unwrap();
+                std::process::exit(64);
+            }
+        },
+        Command::Help { usage } => write!(streams.out, "{}", usage).unwrap(),
     }
 }
 
-fn run_file(path: &str) {
-    match std::fs::read_to_string(path) {
+/// Reads the script at `path`, or standard input when `path` is `-`.
+fn load(path: &str, streams: &mut Streams) -> (SourceMap, FileId) {
+    let (name, contents) = if path == "-" {
+        let mut contents = String::new();
+        let result = streams.input.read_to_string(&mut contents);
+        ("<stdin>", result.map(|_| contents))
+    } else {
+        (path, std::fs::read_to_string(path))
+    };
+    match contents {
         Ok(contents) => {
-            run(&contents);
+            let mut sources = SourceMap::new();
+            let file = sources.add(name, contents);
+            (sources, file)
         }
         Err(e) => {
-            eprintln!("Error reading file '{}': {}", path, e);
+            writeln!(streams.err, "Error reading file '{}': {}", name, e).unwrap();
             std::process::exit(1);
         }
     }
 }
 
-fn run_prompt() {
+fn run_file(path: &str, streams: &mut Streams, lints: &LintConfig) {
+    let (sources, file) = load(path, streams);
+    if !run(&sources, file, streams, lints) {
+        std::process::exit(1);
+    }
+}
+
+const PROMPT_HELP: &str = "\
+Commands:
+  :help            Show this list
+  :quit            Leave the prompt
+  :load <file>     Run a script as part of this session
+  :reset           Forget this session's entries
+  :tokens <code>   Print a table of the tokens in some code
+  :time <code>     Run some code and report how long it took
+  :save <file>     Write this session's accepted entries to a script
+";
+
+/// What the prompt remembers between entries.
+#[derive(Default)]
+struct Session {
+    sources: SourceMap,
+    entries: usize,
+    /// Entries that ran without errors, in order, for `:save`.
+    accepted: Vec<String>,
+}
+
+impl Session {
+    fn add(&mut self, source: String) -> FileId {
+        self.entries += 1;
+        self.sources.add(format!("<repl:{}>", self.entries), source)
+    }
+
+    /// Runs `source` as the next entry, remembering it if it succeeds.
+    fn run(&mut self, source: String, streams: &mut Streams, lints: &LintConfig) -> bool {
+        let file = self.add(source);
+        self.run_file(file, streams, lints)
+    }
+
+    /// Runs `source` under its own `name`, such as the path of a `:load`ed
+    /// script, so diagnostics point into that file.
+    fn run_named(
+        &mut self,
+        name: &str,
+        source: String,
+        streams: &mut Streams,
+        lints: &LintConfig,
+    ) -> bool {
+        let file = self.sources.add(name, source);
+        self.run_file(file, streams, lints)
+    }
+
+    fn run_file(&mut self, file: FileId, streams: &mut Streams, lints: &LintConfig) -> bool {
+        let succeeded = run(&self.sources, file, streams, lints);
+        if succeeded {
+            let source = self.sources.get(file).source();
+            self.accepted.push(source.to_string());
+        }
+        succeeded
+    }
+}
+
+/// Reads entries line by line. An entry that stops inside a string or an
+/// open bracket continues on the next line under a `... ` prompt until it
+/// is complete; Ctrl-C drops it and end of input submits it as it stands. A
+/// line starting with `:` is a command for the prompt itself.
+fn run_prompt(streams: &mut Streams, lints: &LintConfig) {
+    let history = if streams.interactive {
+        History::load(History::default_path())
+    } else {
+        History::default()
+    };
+    let mut editor = LineEditor::new(history);
+    let mut session = Session::default();
+    let mut input = String::new();
     loop {
-        print!("> ");
-        io::stdout().flush().unwrap();
-        let mut input = String::new();
-        match io::stdin().read_line(&mut input) {
-            Ok(_) => {
-                run(&input);
+        let prompt = if input.is_empty() { "> " } else { "... " };
+        match editor.read_line(prompt, streams) {
+            Ok(None) => {
+                writeln!(streams.out).unwrap();
+                if !input.is_empty() {
+                    session.run(input, streams, lints);
+                }
+                return;
             }
+            Ok(Some(line)) => {
+                if let Some(command) = line.trim().strip_prefix(':').filter(|_| input.is_empty()) {
+                    if !run_command(command, &mut session, streams, lints) {
+                        return;
+                    }
+                    continue;
+                }
+                input.push_str(&line);
+                input.push('\n');
+                if scanner::is_incomplete(&input) {
+                    continue;
+                }
+                session.run(std::mem::take(&mut input), streams, lints);
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => input.clear(),
             Err(e) => {
-                eprintln!("Error reading input: {}", e);
+                writeln!(streams.err, "Error reading input: {}", e).unwrap();
                 std::process::exit(1);
             }
         }
     }
 }
 
-fn run(source: &str) {
-    match scanner::scan_tokens(source) {
-        Ok(tokens) => {
+/// Runs a prompt command, given without its `:`. Returns false for `:quit`.
+fn run_command(
+    command: &str,
+    session: &mut Session,
+    streams: &mut Streams,
+    lints: &LintConfig,
+) -> bool {
+    let (name, argument) = match command.split_once(char::is_whitespace) {
+        Some((name, argument)) => (name, argument.trim()),
+        None => (command, ""),
+    };
+    match name {
+        "help" => write!(streams.out, "{}", PROMPT_HELP).unwrap(),
+        "quit" => return false,
+        "reset" => *session = Session::default(),
+        "load" | "tokens" | "time" | "save" if argument.is_empty() => {
+            writeln!(streams.err, "Missing argument for ':{}'", name).unwrap()
+        }
+        "load" => match std::fs::read_to_string(argument) {
+            Ok(mut source) => {
+                if !source.ends_with('\n') {
+                    source.push('\n');
+                }
+                session.run_named(argument, source, streams, lints);
+            }
+            Err(e) => writeln!(streams.err, "Error reading file '{}': {}", argument, e).unwrap(),
+        },
+        "tokens" => {
+            let file = session.add(format!("{}\n", argument));
+            if let Some(tokens) = scan(&session.sources, file, streams, lints) {
+                write_tokens(&session.sources, &tokens, TokenFormat::Table, streams);
+            }
+        }
+        "time" => {
+            let start = std::time::Instant::now();
+            session.run(format!("{}\n", argument), streams, lints);
+            writeln!(streams.out, "Took {:?}", start.elapsed()).unwrap();
+        }
+        "save" => match std::fs::write(argument, session.accepted.concat()) {
+            Ok(()) => writeln!(
+                streams.out,
+                "Saved {} entries to '{}'",
+                session.accepted.len(),
+                argument
+            )
+            .unwrap(),
+            Err(e) => writeln!(streams.err, "Error writing file '{}': {}", argument, e).unwrap(),
+        },
+        _ => writeln!(
+            streams.err,
+            "Unknown command ':{}'. Type :help for a list of commands.",
+            name
+        )
+        .unwrap(),
+    }
+    true
+}
+
+/// Runs `file`, returning whether it succeeded. Diagnostics have already
+/// been reported; callers decide whether a failure ends the process.
+fn run(sources: &SourceMap, file: FileId, streams: &mut Streams, lints: &LintConfig) -> bool {
+    match scan(sources, file, streams, lints) {
+        Some(tokens) => {
             for token in tokens {
-                println!("{:?}", token);
+                writeln!(streams.out, "{:?}", token).unwrap();
             }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Scans and lints `file`, reporting every diagnostic. Returns the tokens
+/// unless there were errors or denied lints.
+fn scan<'a>(
+    sources: &'a SourceMap,
+    file: FileId,
+    streams: &mut Streams,
+    lints: &LintConfig,
+) -> Option<Vec<Token<'a>>> {
+    let (diagnostics, tokens) = match scanner::scan_tokens(file, sources.get(file).source()) {
+        Ok(tokens) => {
+            let warnings = lint::check(sources, file, &tokens, lints);
+            let failed = warnings
+                .iter()
+                .any(|warning| warning.severity == Severity::Error);
+            (warnings, (!failed).then_some(tokens))
         }
-        Err(errors) => {
-            for error in errors {
-                eprintln!("{}", error);
+        Err(errors) => (
+            errors.iter().map(|error| error.to_diagnostic()).collect(),
+            None,
+        ),
+    };
+
+    for diagnostic in &diagnostics {
+        diagnostic
+            .emit(
+                sources,
+                streams.error_format,
+                streams.color,
+                &mut streams.err,
+            )
+            .unwrap();
+    }
+    tokens
+}
+
+fn write_tokens(sources: &SourceMap, tokens: &[Token], format: TokenFormat, streams: &mut Streams) {
+    for token in tokens {
+        let span = token.span();
+        let location = sources.location(span);
+        let literal = match token {
+            Token::String { literal, .. } => literal.map(json_string),
+            Token::Integer { literal, .. } => literal.map(|value| value.to_string()),
+            Token::Float { literal, .. } => literal.map(|value| format!("{:?}", value)),
+            _ => None,
+        };
+        match format {
+            TokenFormat::Table => {
+                let row = format!(
+                    "{:<9} {:<14} {:<16} {}",
+                    format!("{}:{}", location.line, location.column),
+                    token.kind(),
+                    token.lexeme().replace('\n', "\\n"),
+                    literal.unwrap_or_default()
+                );
+                writeln!(streams.out, "{}", row.trim_end())
             }
-            std::process::exit(1);
+            TokenFormat::Json => writeln!(
+                streams.out,
+                "{{\"kind\":{},\"lexeme\":{},\"literal\":{},\"line\":{},\"column\":{},\
+                 \"start\":{},\"end\":{}}}",
+                json_string(token.kind()),
+                json_string(token.lexeme()),
+                literal.unwrap_or_else(|| "null".to_string()),
+                location.line,
+                location.column,
+                span.start,
+                span.end
+            ),
+        }
+        .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        diagnostic::ErrorFormat, lint::LintConfig, run, run_prompt, source_map::SourceMap,
+        streams::Streams,
+    };
+    use std::{
+        cell::RefCell,
+        io::{self, Write},
+        rc::Rc,
+    };
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    impl SharedBuffer {
+        fn contents(&self) -> String {
+            String::from_utf8(self.0.borrow().clone()).unwrap()
+        }
+    }
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
         }
     }
+
+    fn streams(input: &str) -> (Streams, SharedBuffer, SharedBuffer) {
+        let out = SharedBuffer::default();
+        let err = SharedBuffer::default();
+        let streams = Streams {
+            out: Box::new(out.clone()),
+            err: Box::new(err.clone()),
+            input: Box::new(io::Cursor::new(input.to_string())),
+            color: false,
+            interactive: false,
+            error_format: ErrorFormat::Human,
+        };
+        (streams, out, err)
+    }
+
+    #[test]
+    fn run_writes_tokens_to_the_output_sink() {
+        let (mut streams, out, err) = streams("");
+
+        let mut sources = SourceMap::new();
+        let file = sources.add("test.lox", "1");
+        assert!(run(&sources, file, &mut streams, &LintConfig::default()));
+
+        assert_eq!(
+            out.contents(),
+            "Integer { lexeme: \"1\", literal: Some(1), line: 1, \
+             span: Span { file: FileId(0), start: 0, end: 1 } }\n\
+             Eof { lexeme: \"\", literal: None, line: 1, \
+             span: Span { file: FileId(0), start: 1, end: 1 } }\n"
+        );
+        assert!(err.contents().is_empty());
+    }
+
+    #[test]
+    fn prompt_stops_at_end_of_input() {
+        let (mut streams, out, err) = streams("1\n");
+        run_prompt(&mut streams, &LintConfig::default());
+
+        assert!(out.contents().starts_with("> Integer"));
+        assert!(out.contents().ends_with("> \n"));
+        assert!(err.contents().is_empty());
+    }
+
+    #[test]
+    fn prompt_reports_errors_and_keeps_going() {
+        let (mut streams, out, err) = streams("@\n1\n");
+        run_prompt(&mut streams, &LintConfig::default());
+
+        assert!(err.contents().contains("Unexpected character: @"));
+        assert!(err.contents().contains("<repl:1>"));
+        assert!(out.contents().contains("Integer { lexeme: \"1\""));
+    }
+
+    #[test]
+    fn prompt_continues_incomplete_entries() {
+        let (mut streams, out, err) = streams("(1\n2)\n{\n  var a = 1;\n\n  print a;\n}\n");
+        run_prompt(&mut streams, &LintConfig::default());
+
+        let out = out.contents();
+        assert!(out.starts_with("> ... LeftParen"));
+        assert!(out.contains("RightParen { lexeme: \")\", literal: None, line: 2"));
+        assert!(out.contains("RightBrace { lexeme: \"}\", literal: None, line: 5"));
+        assert_eq!(out.matches("... ").count(), 5);
+        assert!(out.ends_with("> \n"));
+        assert!(err.contents().is_empty());
+    }
+
+    #[test]
+    fn prompt_runs_commands() {
+        let (mut streams, out, err) = streams(":help\n:bogus\n:tokens\n:quit\n1\n");
+        run_prompt(&mut streams, &LintConfig::default());
+
+        assert!(out.contents().contains(":load <file>"));
+        assert!(!out.contents().contains("Integer"));
+        assert!(err.contents().contains("Unknown command ':bogus'"));
+        assert!(err.contents().contains("Missing argument for ':tokens'"));
+    }
+
+    #[test]
+    fn prompt_saves_accepted_entries() {
+        let path = std::env::temp_dir().join(format!("rustylox_save_{}.lox", std::process::id()));
+        let input = format!("print 1;\n@\n:tokens 2\n(3\n)\n:save {}\n", path.display());
+        let (mut streams, out, _) = streams(&input);
+        run_prompt(&mut streams, &LintConfig::default());
+
+        let saved = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(saved, "print 1;\n(3\n)\n");
+        assert!(out.contents().contains("Saved 2 entries"));
+        assert!(out
+            .contents()
+            .contains("1:1       Integer        2                2"));
+    }
+
+    #[test]
+    fn prompt_reports_loaded_files_under_their_path() {
+        let path = std::env::temp_dir().join(format!("rustylox_load_{}.lox", std::process::id()));
+        std::fs::write(&path, "print 1;\n@\n").unwrap();
+        let (mut streams, _, err) = streams(&format!(":load {}\n", path.display()));
+        run_prompt(&mut streams, &LintConfig::default());
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(err.contents().contains(&format!("{}:2:1", path.display())));
+        assert!(!err.contents().contains("<repl:"));
+    }
 }
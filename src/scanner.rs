@@ -1,6 +1,257 @@
-use crate::token::Token;
+use crate::{
+    diagnostic::Diagnostic,
+    source_map::{FileId, Span},
+    token::Token,
+};
+use std::fmt;
 
-pub fn scan_tokens(source: &str) -> Result<Vec<Token>, Vec<String>> {
+#[derive(Debug, PartialEq)]
+pub struct ScanError {
+    pub kind: ScanErrorKind,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ScanErrorKind {
+    UnterminatedString,
+    InvalidFloat,
+    FloatEndsWithDot,
+    InvalidInteger,
+    UnexpectedCharacter(char),
+}
+
+impl ScanErrorKind {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ScanErrorKind::UnterminatedString => "L0001",
+            ScanErrorKind::InvalidFloat => "L0002",
+            ScanErrorKind::FloatEndsWithDot => "L0003",
+            ScanErrorKind::InvalidInteger => "L0004",
+            ScanErrorKind::UnexpectedCharacter(_) => "L0005",
+        }
+    }
+}
+
+impl fmt::Display for ScanErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScanErrorKind::UnterminatedString => write!(f, "Unterminated string"),
+            ScanErrorKind::InvalidFloat => write!(f, "Invalid float"),
+            ScanErrorKind::FloatEndsWithDot => write!(f, "Invalid float - last char is a ."),
+            ScanErrorKind::InvalidInteger => write!(f, "Invalid integer"),
+            ScanErrorKind::UnexpectedCharacter(c) => write!(f, "Unexpected character: {}", c),
+        }
+    }
+}
+
+impl ScanError {
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let Span { file, start, end } = self.span;
+        let message = self.kind.to_string();
+        let diagnostic = match self.kind {
+            ScanErrorKind::UnterminatedString => {
+                Diagnostic::error(message, Span::new(file, end, end))
+                    .with_label("source ends here")
+                    .with_secondary(Span::new(file, start, start + 1), "string starts here")
+                    .with_help("add a closing `\"`")
+            }
+            ScanErrorKind::InvalidFloat => Diagnostic::error(message, self.span)
+                .with_label("not a valid number")
+                .with_note("a float literal has exactly one `.` between digits"),
+            ScanErrorKind::FloatEndsWithDot => Diagnostic::error(message, self.span)
+                .with_label("expected a digit after the `.`")
+                .with_suggestion(Span::new(file, end, end), "add a fractional digit", "0"),
+            ScanErrorKind::InvalidInteger => Diagnostic::error(message, self.span)
+                .with_label("does not fit in 64 bits")
+                .with_note(format!("integer literals can be at most {}", i64::MAX)),
+            ScanErrorKind::UnexpectedCharacter(_) => {
+                Diagnostic::error(message, self.span).with_label("not valid in Lox source")
+            }
+        };
+        diagnostic.with_code(self.kind.code())
+    }
+}
+
+type KeywordToken = for<'a> fn(&'a str, usize, Span) -> Token<'a>;
+
+/// Every reserved word and the token it scans to, in alphabetical order.
+const KEYWORD_TOKENS: [(&str, KeywordToken); 27] = [
+    ("and", |lexeme, line, span| Token::And {
+        lexeme,
+        literal: None,
+        line,
+        span,
+    }),
+    ("break", |lexeme, line, span| Token::Break {
+        lexeme,
+        literal: None,
+        line,
+        span,
+    }),
+    ("catch", |lexeme, line, span| Token::Catch {
+        lexeme,
+        literal: None,
+        line,
+        span,
+    }),
+    ("class", |lexeme, line, span| Token::Class {
+        lexeme,
+        literal: None,
+        line,
+        span,
+    }),
+    ("const", |lexeme, line, span| Token::Const {
+        lexeme,
+        literal: None,
+        line,
+        span,
+    }),
+    ("continue", |lexeme, line, span| Token::Continue {
+        lexeme,
+        literal: None,
+        line,
+        span,
+    }),
+    ("else", |lexeme, line, span| Token::Else {
+        lexeme,
+        literal: None,
+        line,
+        span,
+    }),
+    ("export", |lexeme, line, span| Token::Export {
+        lexeme,
+        literal: None,
+        line,
+        span,
+    }),
+    ("false", |lexeme, line, span| Token::False {
+        lexeme,
+        literal: None,
+        line,
+        span,
+    }),
+    ("finally", |lexeme, line, span| Token::Finally {
+        lexeme,
+        literal: None,
+        line,
+        span,
+    }),
+    ("for", |lexeme, line, span| Token::For {
+        lexeme,
+        literal: None,
+        line,
+        span,
+    }),
+    ("fun", |lexeme, line, span| Token::Fun {
+        lexeme,
+        literal: None,
+        line,
+        span,
+    }),
+    ("if", |lexeme, line, span| Token::If {
+        lexeme,
+        literal: None,
+        line,
+        span,
+    }),
+    ("import", |lexeme, line, span| Token::Import {
+        lexeme,
+        literal: None,
+        line,
+        span,
+    }),
+    ("in", |lexeme, line, span| Token::In {
+        lexeme,
+        literal: None,
+        line,
+        span,
+    }),
+    ("nil", |lexeme, line, span| Token::Nil {
+        lexeme,
+        literal: None,
+        line,
+        span,
+    }),
+    ("or", |lexeme, line, span| Token::Or {
+        lexeme,
+        literal: None,
+        line,
+        span,
+    }),
+    ("print", |lexeme, line, span| Token::Print {
+        lexeme,
+        literal: None,
+        line,
+        span,
+    }),
+    ("return", |lexeme, line, span| Token::Return {
+        lexeme,
+        literal: None,
+        line,
+        span,
+    }),
+    ("super", |lexeme, line, span| Token::Super {
+        lexeme,
+        literal: None,
+        line,
+        span,
+    }),
+    ("this", |lexeme, line, span| Token::This {
+        lexeme,
+        literal: None,
+        line,
+        span,
+    }),
+    ("throw", |lexeme, line, span| Token::Throw {
+        lexeme,
+        literal: None,
+        line,
+        span,
+    }),
+    ("true", |lexeme, line, span| Token::True {
+        lexeme,
+        literal: None,
+        line,
+        span,
+    }),
+    ("try", |lexeme, line, span| Token::Try {
+        lexeme,
+        literal: None,
+        line,
+        span,
+    }),
+    ("var", |lexeme, line, span| Token::Var {
+        lexeme,
+        literal: None,
+        line,
+        span,
+    }),
+    ("while", |lexeme, line, span| Token::While {
+        lexeme,
+        literal: None,
+        line,
+        span,
+    }),
+    ("yield", |lexeme, line, span| Token::Yield {
+        lexeme,
+        literal: None,
+        line,
+        span,
+    }),
+];
+
+/// Every reserved word, in alphabetical order.
+pub const KEYWORDS: [&str; KEYWORD_TOKENS.len()] = {
+    let mut keywords = [""; KEYWORD_TOKENS.len()];
+    let mut idx = 0;
+    while idx < keywords.len() {
+        keywords[idx] = KEYWORD_TOKENS[idx].0;
+        idx += 1;
+    }
+    keywords
+};
+
+pub fn scan_tokens(file: FileId, source: &str) -> Result<Vec<Token<'_>>, Vec<ScanError>> {
     let mut tokens = Vec::new();
     let mut errors = Vec::new();
     let mut line = 1;
@@ -12,82 +263,218 @@ pub fn scan_tokens(source: &str) -> Result<Vec<Token>, Vec<String>> {
                 lexeme: &source[idx..idx + 1],
                 literal: None,
                 line,
+                span: Span::new(file, idx, idx + 1),
             }),
             ')' => tokens.push(Token::RightParen {
                 lexeme: &source[idx..idx + 1],
                 literal: None,
                 line,
+                span: Span::new(file, idx, idx + 1),
             }),
             '{' => tokens.push(Token::LeftBrace {
                 lexeme: &source[idx..idx + 1],
                 literal: None,
                 line,
+                span: Span::new(file, idx, idx + 1),
             }),
             '}' => tokens.push(Token::RightBrace {
                 lexeme: &source[idx..idx + 1],
                 literal: None,
                 line,
+                span: Span::new(file, idx, idx + 1),
+            }),
+            '[' => tokens.push(Token::LeftBracket {
+                lexeme: &source[idx..idx + 1],
+                literal: None,
+                line,
+                span: Span::new(file, idx, idx + 1),
+            }),
+            ']' => tokens.push(Token::RightBracket {
+                lexeme: &source[idx..idx + 1],
+                literal: None,
+                line,
+                span: Span::new(file, idx, idx + 1),
             }),
             ',' => tokens.push(Token::Comma {
                 lexeme: &source[idx..idx + 1],
                 literal: None,
                 line,
+                span: Span::new(file, idx, idx + 1),
+            }),
+            ':' => tokens.push(Token::Colon {
+                lexeme: &source[idx..idx + 1],
+                literal: None,
+                line,
+                span: Span::new(file, idx, idx + 1),
             }),
             '.' => tokens.push(Token::Dot {
                 lexeme: &source[idx..idx + 1],
                 literal: None,
                 line,
+                span: Span::new(file, idx, idx + 1),
             }),
-            '-' => tokens.push(Token::Minus {
+            ';' => tokens.push(Token::Semicolon {
                 lexeme: &source[idx..idx + 1],
                 literal: None,
                 line,
+                span: Span::new(file, idx, idx + 1),
             }),
-            '+' => tokens.push(Token::Plus {
+            '?' => tokens.push(Token::Question {
                 lexeme: &source[idx..idx + 1],
                 literal: None,
                 line,
+                span: Span::new(file, idx, idx + 1),
             }),
-            ';' => tokens.push(Token::Semicolon {
+            '&' => tokens.push(Token::Ampersand {
+                lexeme: &source[idx..idx + 1],
+                literal: None,
+                line,
+                span: Span::new(file, idx, idx + 1),
+            }),
+            '^' => tokens.push(Token::Caret {
                 lexeme: &source[idx..idx + 1],
                 literal: None,
                 line,
+                span: Span::new(file, idx, idx + 1),
             }),
-            '*' => tokens.push(Token::Star {
+            '|' => tokens.push(Token::Pipe {
                 lexeme: &source[idx..idx + 1],
                 literal: None,
                 line,
+                span: Span::new(file, idx, idx + 1),
             }),
 
-            '!' | '=' | '<' | '>' | '/' => {
+            '!' | '=' | '<' | '>' | '/' | '+' | '-' | '*' | '%' | '~' => {
                 let next_char = chars.peek().map(|&(_, nc)| nc);
                 match (c, next_char) {
-                    ('!', Some('=')) | ('=', Some('=')) | ('<', Some('=')) | ('>', Some('=')) => {
+                    ('!', Some('='))
+                    | ('=', Some('='))
+                    | ('<', Some('='))
+                    | ('>', Some('='))
+                    | ('/', Some('='))
+                    | ('+', Some('='))
+                    | ('-', Some('='))
+                    | ('*', Some('='))
+                    | ('%', Some('=')) => {
                         chars.next();
                         tokens.push(match c {
                             '!' => Token::BangEqual {
                                 lexeme: &source[idx..idx + 2],
                                 literal: None,
                                 line,
+                                span: Span::new(file, idx, idx + 2),
                             },
                             '=' => Token::EqualEqual {
                                 lexeme: &source[idx..idx + 2],
                                 literal: None,
                                 line,
+                                span: Span::new(file, idx, idx + 2),
                             },
                             '<' => Token::LessEqual {
                                 lexeme: &source[idx..idx + 2],
                                 literal: None,
                                 line,
+                                span: Span::new(file, idx, idx + 2),
                             },
                             '>' => Token::GreaterEqual {
                                 lexeme: &source[idx..idx + 2],
                                 literal: None,
                                 line,
+                                span: Span::new(file, idx, idx + 2),
+                            },
+                            '/' => Token::SlashEqual {
+                                lexeme: &source[idx..idx + 2],
+                                literal: None,
+                                line,
+                                span: Span::new(file, idx, idx + 2),
+                            },
+                            '+' => Token::PlusEqual {
+                                lexeme: &source[idx..idx + 2],
+                                literal: None,
+                                line,
+                                span: Span::new(file, idx, idx + 2),
+                            },
+                            '-' => Token::MinusEqual {
+                                lexeme: &source[idx..idx + 2],
+                                literal: None,
+                                line,
+                                span: Span::new(file, idx, idx + 2),
+                            },
+                            '*' => Token::StarEqual {
+                                lexeme: &source[idx..idx + 2],
+                                literal: None,
+                                line,
+                                span: Span::new(file, idx, idx + 2),
+                            },
+                            '%' => Token::PercentEqual {
+                                lexeme: &source[idx..idx + 2],
+                                literal: None,
+                                line,
+                                span: Span::new(file, idx, idx + 2),
+                            },
+                            _ => unreachable!(),
+                        });
+                    }
+                    ('+', Some('+')) | ('-', Some('-')) => {
+                        chars.next();
+                        tokens.push(match c {
+                            '+' => Token::PlusPlus {
+                                lexeme: &source[idx..idx + 2],
+                                literal: None,
+                                line,
+                                span: Span::new(file, idx, idx + 2),
+                            },
+                            '-' => Token::MinusMinus {
+                                lexeme: &source[idx..idx + 2],
+                                literal: None,
+                                line,
+                                span: Span::new(file, idx, idx + 2),
+                            },
+                            _ => unreachable!(),
+                        });
+                    }
+                    // `~//` is a `~` followed by a comment, not `~/` then `/`.
+                    ('<', Some('<')) | ('>', Some('>')) | ('*', Some('*')) | ('~', Some('/'))
+                        if !(c == '~' && source[idx + 1..].starts_with("//")) =>
+                    {
+                        chars.next();
+                        tokens.push(match c {
+                            '<' => Token::LessLess {
+                                lexeme: &source[idx..idx + 2],
+                                literal: None,
+                                line,
+                                span: Span::new(file, idx, idx + 2),
+                            },
+                            '>' => Token::GreaterGreater {
+                                lexeme: &source[idx..idx + 2],
+                                literal: None,
+                                line,
+                                span: Span::new(file, idx, idx + 2),
+                            },
+                            '*' => Token::StarStar {
+                                lexeme: &source[idx..idx + 2],
+                                literal: None,
+                                line,
+                                span: Span::new(file, idx, idx + 2),
+                            },
+                            '~' => Token::TildeSlash {
+                                lexeme: &source[idx..idx + 2],
+                                literal: None,
+                                line,
+                                span: Span::new(file, idx, idx + 2),
                             },
                             _ => unreachable!(),
                         });
                     }
+                    ('=', Some('>')) => {
+                        chars.next();
+                        tokens.push(Token::EqualGreater {
+                            lexeme: &source[idx..idx + 2],
+                            literal: None,
+                            line,
+                            span: Span::new(file, idx, idx + 2),
+                        });
+                    }
                     ('/', Some('/')) => {
                         while let Some((_, next)) = chars.peek() {
                             if *next == '\n' {
@@ -102,26 +489,61 @@ pub fn scan_tokens(source: &str) -> Result<Vec<Token>, Vec<String>> {
                                 lexeme: &source[idx..idx + 1],
                                 literal: None,
                                 line,
+                                span: Span::new(file, idx, idx + 1),
                             },
                             '=' => Token::Equal {
                                 lexeme: &source[idx..idx + 1],
                                 literal: None,
                                 line,
+                                span: Span::new(file, idx, idx + 1),
                             },
                             '<' => Token::Less {
                                 lexeme: &source[idx..idx + 1],
                                 literal: None,
                                 line,
+                                span: Span::new(file, idx, idx + 1),
                             },
                             '>' => Token::Greater {
                                 lexeme: &source[idx..idx + 1],
                                 literal: None,
                                 line,
+                                span: Span::new(file, idx, idx + 1),
                             },
                             '/' => Token::Slash {
                                 lexeme: &source[idx..idx + 1],
                                 literal: None,
                                 line,
+                                span: Span::new(file, idx, idx + 1),
+                            },
+                            '+' => Token::Plus {
+                                lexeme: &source[idx..idx + 1],
+                                literal: None,
+                                line,
+                                span: Span::new(file, idx, idx + 1),
+                            },
+                            '-' => Token::Minus {
+                                lexeme: &source[idx..idx + 1],
+                                literal: None,
+                                line,
+                                span: Span::new(file, idx, idx + 1),
+                            },
+                            '*' => Token::Star {
+                                lexeme: &source[idx..idx + 1],
+                                literal: None,
+                                line,
+                                span: Span::new(file, idx, idx + 1),
+                            },
+                            '%' => Token::Percent {
+                                lexeme: &source[idx..idx + 1],
+                                literal: None,
+                                line,
+                                span: Span::new(file, idx, idx + 1),
+                            },
+                            '~' => Token::Tilde {
+                                lexeme: &source[idx..idx + 1],
+                                literal: None,
+                                line,
+                                span: Span::new(file, idx, idx + 1),
                             },
                             _ => unreachable!(),
                         });
@@ -161,9 +583,13 @@ pub fn scan_tokens(source: &str) -> Result<Vec<Token>, Vec<String>> {
                         lexeme: &source[idx..=end_idx],
                         literal: Some(&source[idx + 1..end_idx]),
                         line,
+                        span: Span::new(file, idx, end_idx + 1),
                     });
                 } else {
-                    errors.push(format!("Unterminated string on line {}", line));
+                    errors.push(ScanError {
+                        kind: ScanErrorKind::UnterminatedString,
+                        span: Span::new(file, idx, source.len()),
+                    });
                 }
             }
 
@@ -187,6 +613,7 @@ pub fn scan_tokens(source: &str) -> Result<Vec<Token>, Vec<String>> {
                 }
 
                 let lexeme = &source[idx..=end_idx];
+                let span = Span::new(file, idx, end_idx + 1);
                 if contains_dot {
                     match lexeme.chars().last() {
                         Some(last_char) if last_char.is_ascii_digit() => {
@@ -195,15 +622,19 @@ pub fn scan_tokens(source: &str) -> Result<Vec<Token>, Vec<String>> {
                                     lexeme,
                                     literal: Some(num),
                                     line,
+                                    span,
+                                }),
+                                Err(_) => errors.push(ScanError {
+                                    kind: ScanErrorKind::InvalidFloat,
+                                    span,
                                 }),
-                                Err(_) => errors.push(format!("Invalid float on line {}", line)),
                             }
                         }
                         _ => {
-                            errors.push(format!(
-                                "Invalid float on line {}  - last char is a .",
-                                line
-                            ));
+                            errors.push(ScanError {
+                                kind: ScanErrorKind::FloatEndsWithDot,
+                                span,
+                            });
                         }
                     }
                 } else {
@@ -212,8 +643,12 @@ pub fn scan_tokens(source: &str) -> Result<Vec<Token>, Vec<String>> {
                             lexeme,
                             literal: Some(num),
                             line,
+                            span,
+                        }),
+                        Err(_) => errors.push(ScanError {
+                            kind: ScanErrorKind::InvalidInteger,
+                            span,
                         }),
-                        Err(_) => errors.push(format!("Invalid integer on line {}", line)),
                     }
                 }
             }
@@ -231,95 +666,24 @@ pub fn scan_tokens(source: &str) -> Result<Vec<Token>, Vec<String>> {
                 }
 
                 let lexeme = &source[idx..=end_idx];
-                tokens.push(match lexeme {
-                    "and" => Token::And {
-                        lexeme,
-                        literal: None,
-                        line,
-                    },
-                    "class" => Token::Class {
-                        lexeme,
-                        literal: None,
-                        line,
-                    },
-                    "else" => Token::Else {
-                        lexeme,
-                        literal: None,
-                        line,
-                    },
-                    "false" => Token::False {
-                        lexeme,
-                        literal: None,
-                        line,
-                    },
-                    "for" => Token::For {
-                        lexeme,
-                        literal: None,
-                        line,
-                    },
-                    "fun" => Token::Fun {
-                        lexeme,
-                        literal: None,
-                        line,
-                    },
-                    "if" => Token::If {
-                        lexeme,
-                        literal: None,
-                        line,
-                    },
-                    "nil" => Token::Nil {
-                        lexeme,
-                        literal: None,
-                        line,
-                    },
-                    "or" => Token::Or {
-                        lexeme,
-                        literal: None,
-                        line,
-                    },
-                    "print" => Token::Print {
-                        lexeme,
-                        literal: None,
-                        line,
-                    },
-                    "return" => Token::Return {
-                        lexeme,
-                        literal: None,
-                        line,
-                    },
-                    "super" => Token::Super {
-                        lexeme,
-                        literal: None,
-                        line,
-                    },
-                    "this" => Token::This {
-                        lexeme,
-                        literal: None,
-                        line,
-                    },
-                    "true" => Token::True {
-                        lexeme,
-                        literal: None,
-                        line,
-                    },
-                    "var" => Token::Var {
-                        lexeme,
-                        literal: None,
-                        line,
-                    },
-                    "while" => Token::While {
-                        lexeme,
-                        literal: None,
-                        line,
-                    },
-                    _ => Token::Identifier {
+                let span = Span::new(file, idx, end_idx + 1);
+                let keyword = KEYWORD_TOKENS
+                    .iter()
+                    .find(|(keyword, _)| *keyword == lexeme);
+                tokens.push(match keyword {
+                    Some((_, token)) => token(lexeme, line, span),
+                    None => Token::Identifier {
                         lexeme,
                         literal: None,
                         line,
+                        span,
                     },
                 })
             }
-            _ => errors.push(format!("Unexpected character: {} on line {}", c, line)),
+            _ => errors.push(ScanError {
+                kind: ScanErrorKind::UnexpectedCharacter(c),
+                span: Span::new(file, idx, idx + c.len_utf8()),
+            }),
         };
     }
 
@@ -327,6 +691,7 @@ pub fn scan_tokens(source: &str) -> Result<Vec<Token>, Vec<String>> {
         lexeme: "",
         literal: None,
         line,
+        span: Span::new(file, source.len(), source.len()),
     });
 
     if !errors.is_empty() {
@@ -336,23 +701,129 @@ pub fn scan_tokens(source: &str) -> Result<Vec<Token>, Vec<String>> {
     Ok(tokens)
 }
 
-#[cfg(test)]
-mod tests {
+/// A `// lox-allow(name, ...)` comment naming lints to silence.
+#[derive(Debug, PartialEq)]
+pub struct Pragma<'a> {
+    pub span: Span,
+    pub lints: Vec<(&'a str, Span)>,
+}
 
-    mod int_and_float {
-        use crate::{scanner::scan_tokens, token::Token};
+/// Finds lint pragmas in the comments between `tokens`, which must be the
+/// tokens scanned from `source`. Comments only ever sit in the gaps between
+/// tokens, so `//` inside a string literal is never mistaken for one.
+pub fn scan_pragmas<'a>(source: &'a str, tokens: &[Token]) -> Vec<Pragma<'a>> {
+    let mut pragmas = Vec::new();
+    let mut gap_start = 0;
 
-        #[test]
-        fn simple_integer() {
-            let source = "123";
-            let tokens = scan_tokens(source).unwrap();
-            assert_eq!(tokens.len(), 2);
-            assert_eq!(
-                tokens[0],
-                Token::Integer {
-                    lexeme: "123",
-                    literal: Some(123),
-                    line: 1,
+    for token in tokens {
+        let span = token.span();
+        let mut line_start = gap_start;
+        for line in source[gap_start..span.start].split_inclusive('\n') {
+            let comment_start = line.find("//").map(|idx| line_start + idx);
+            line_start += line.len();
+            let Some(comment_start) = comment_start else {
+                continue;
+            };
+
+            let comment = source[comment_start..line_start].trim_end();
+            let body = comment[2..].trim_start();
+            let Some(list) = body
+                .strip_prefix("lox-allow(")
+                .and_then(|rest| rest.find(')').map(|end| &rest[..end]))
+            else {
+                continue;
+            };
+
+            let mut name_start = comment_start + (comment.len() - body.len()) + "lox-allow(".len();
+            let mut lints = Vec::new();
+            for name in list.split(',') {
+                let trimmed = name.trim();
+                if !trimmed.is_empty() {
+                    let start = name_start + (name.len() - name.trim_start().len());
+                    lints.push((trimmed, Span::new(span.file, start, start + trimmed.len())));
+                }
+                name_start += name.len() + 1;
+            }
+            pragmas.push(Pragma {
+                span: Span::new(span.file, comment_start, comment_start + comment.len()),
+                lints,
+            });
+        }
+        gap_start = span.end;
+    }
+
+    pragmas
+}
+
+/// Whether `source` stops partway through a construct: inside a string, or
+/// with more brackets opened than closed. The prompt uses this to ask for
+/// more lines instead of submitting the entry. Any other scan error, or a
+/// closing bracket with nothing left to close, makes the input invalid
+/// rather than incomplete, so it is submitted straight away. Scan errors
+/// are reported then; stray brackets will be once there is a parser.
+pub fn is_incomplete(source: &str) -> bool {
+    let string_start = match scan_tokens(FileId(0), source) {
+        Ok(tokens) => return open_brackets(&tokens).is_some_and(|open| open > 0),
+        Err(errors) => match errors.as_slice() {
+            [ScanError {
+                kind: ScanErrorKind::UnterminatedString,
+                span,
+            }] => span.start,
+            _ => return false,
+        },
+    };
+    // The unterminated string runs to the end, so only what comes before
+    // it can still hold a stray closing bracket.
+    scan_tokens(FileId(0), &source[..string_start])
+        .is_ok_and(|tokens| open_brackets(&tokens).is_some())
+}
+
+/// How many brackets `tokens` leave open, or `None` if one closes with
+/// nothing left to close.
+fn open_brackets(tokens: &[Token]) -> Option<usize> {
+    let mut depth: usize = 0;
+    for token in tokens {
+        match token {
+            Token::LeftParen { .. } | Token::LeftBrace { .. } | Token::LeftBracket { .. } => {
+                depth += 1
+            }
+            Token::RightParen { .. } | Token::RightBrace { .. } | Token::RightBracket { .. } => {
+                depth = depth.checked_sub(1)?
+            }
+            _ => {}
+        }
+    }
+    Some(depth)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::source_map::{FileId, Span};
+
+    fn span(start: usize, end: usize) -> Span {
+        Span::new(FileId(0), start, end)
+    }
+
+    mod int_and_float {
+        use super::span;
+        use crate::{
+            scanner::{scan_tokens, ScanError, ScanErrorKind},
+            source_map::FileId,
+            token::Token,
+        };
+
+        #[test]
+        fn simple_integer() {
+            let source = "123";
+            let tokens = scan_tokens(FileId(0), source).unwrap();
+            assert_eq!(tokens.len(), 2);
+            assert_eq!(
+                tokens[0],
+                Token::Integer {
+                    lexeme: "123",
+                    literal: Some(123),
+                    line: 1,
+                    span: span(0, 3),
                 }
             );
         }
@@ -360,7 +831,7 @@ mod tests {
         #[test]
         fn leading_zeros_integer() {
             let source = "007";
-            let tokens = scan_tokens(source).unwrap();
+            let tokens = scan_tokens(FileId(0), source).unwrap();
             assert_eq!(tokens.len(), 2);
             assert_eq!(
                 tokens[0],
@@ -368,6 +839,7 @@ mod tests {
                     lexeme: "007",
                     literal: Some(7),
                     line: 1,
+                    span: span(0, 3),
                 }
             );
         }
@@ -375,7 +847,7 @@ mod tests {
         #[test]
         fn test_simple_float() {
             let source = "123.456";
-            let tokens = scan_tokens(source).unwrap();
+            let tokens = scan_tokens(FileId(0), source).unwrap();
             assert_eq!(tokens.len(), 2);
             assert_eq!(
                 tokens[0],
@@ -383,6 +855,7 @@ mod tests {
                     lexeme: "123.456",
                     literal: Some(123.456),
                     line: 1,
+                    span: span(0, 7),
                 }
             );
         }
@@ -390,31 +863,48 @@ mod tests {
         #[test]
         fn handles_invalid_float_gracefully() {
             let source = "123.45.67";
-            let result = scan_tokens(source);
+            let result = scan_tokens(FileId(0), source);
             assert!(result.is_err());
             let errors = result.err().unwrap();
             assert!(!errors.is_empty());
-            assert!(errors[0].contains("Invalid float on line 1"));
+            assert_eq!(
+                errors[0],
+                ScanError {
+                    kind: ScanErrorKind::InvalidFloat,
+                    span: span(0, 9),
+                }
+            );
         }
 
         #[test]
         fn fails_to_parse_float_with_dot_at_end() {
             let source = "123.";
-            let result = scan_tokens(source);
+            let result = scan_tokens(FileId(0), source);
             assert!(result.is_err());
             let errors = result.err().unwrap();
             assert!(!errors.is_empty());
-            assert!(errors[0].contains("Invalid float on line 1  - last char is a ."));
+            assert_eq!(
+                errors[0],
+                ScanError {
+                    kind: ScanErrorKind::FloatEndsWithDot,
+                    span: span(0, 4),
+                }
+            );
         }
     }
 
     mod strings {
-        use crate::{scanner::scan_tokens, token::Token};
+        use super::span;
+        use crate::{
+            scanner::{scan_tokens, ScanError, ScanErrorKind},
+            source_map::FileId,
+            token::Token,
+        };
 
         #[test]
         fn parses_terminated_strings_correctly() {
             let source = "\"This is a string.\"";
-            let tokens = scan_tokens(source).unwrap();
+            let tokens = scan_tokens(FileId(0), source).unwrap();
             assert_eq!(tokens.len(), 2);
 
             assert_eq!(
@@ -423,6 +913,7 @@ mod tests {
                     lexeme: "\"This is a string.\"",
                     literal: Some("This is a string."),
                     line: 1,
+                    span: span(0, 19),
                 }
             );
         }
@@ -430,17 +921,23 @@ mod tests {
         #[test]
         fn reports_unterminated_strings() {
             let source = "\"This string has no end";
-            let result = scan_tokens(source);
+            let result = scan_tokens(FileId(0), source);
             assert!(result.is_err());
             let errors = result.err().unwrap();
             assert!(!errors.is_empty());
-            assert!(errors[0].contains("Unterminated string"));
+            assert_eq!(
+                errors[0],
+                ScanError {
+                    kind: ScanErrorKind::UnterminatedString,
+                    span: span(0, 23),
+                }
+            );
         }
 
         #[test]
         fn handles_strings_with_newlines() {
             let source = "\"This is a string\nwith a newline.\"";
-            let tokens = scan_tokens(source).unwrap();
+            let tokens = scan_tokens(FileId(0), source).unwrap();
             assert_eq!(tokens.len(), 2);
             assert_eq!(
                 tokens[0],
@@ -448,6 +945,595 @@ mod tests {
                     lexeme: "\"This is a string\nwith a newline.\"",
                     literal: Some("This is a string\nwith a newline."),
                     line: 2,
+                    span: span(0, 34),
+                }
+            );
+        }
+    }
+
+    mod punctuation {
+        use super::span;
+        use crate::{
+            scanner::{scan_tokens, ScanError, ScanErrorKind},
+            source_map::FileId,
+            token::Token,
+        };
+
+        #[test]
+        fn scans_list_brackets() {
+            let source = "xs[-1]";
+            let tokens = scan_tokens(FileId(0), source).unwrap();
+            assert_eq!(tokens.len(), 6);
+            assert_eq!(
+                tokens[1],
+                Token::LeftBracket {
+                    lexeme: "[",
+                    literal: None,
+                    line: 1,
+                    span: span(2, 3),
+                }
+            );
+            assert_eq!(
+                tokens[4],
+                Token::RightBracket {
+                    lexeme: "]",
+                    literal: None,
+                    line: 1,
+                    span: span(5, 6),
+                }
+            );
+        }
+
+        #[test]
+        fn scans_map_entry_colon() {
+            let source = "{\"a\": 1}";
+            let tokens = scan_tokens(FileId(0), source).unwrap();
+            assert_eq!(tokens.len(), 6);
+            assert_eq!(
+                tokens[2],
+                Token::Colon {
+                    lexeme: ":",
+                    literal: None,
+                    line: 1,
+                    span: span(4, 5),
+                }
+            );
+        }
+        #[test]
+        fn scans_arrow_distinctly_from_comparisons() {
+            let source = "=> == >=";
+            let tokens = scan_tokens(FileId(0), source).unwrap();
+            assert_eq!(tokens.len(), 4);
+            assert_eq!(
+                tokens[0],
+                Token::EqualGreater {
+                    lexeme: "=>",
+                    literal: None,
+                    line: 1,
+                    span: span(0, 2),
+                }
+            );
+            assert_eq!(
+                tokens[1],
+                Token::EqualEqual {
+                    lexeme: "==",
+                    literal: None,
+                    line: 1,
+                    span: span(3, 5),
+                }
+            );
+            assert_eq!(
+                tokens[2],
+                Token::GreaterEqual {
+                    lexeme: ">=",
+                    literal: None,
+                    line: 1,
+                    span: span(6, 8),
+                }
+            );
+        }
+        #[test]
+        fn scans_compound_assignment_operators() {
+            let source = "+= -= *= /= %=";
+            let tokens = scan_tokens(FileId(0), source).unwrap();
+            assert_eq!(
+                tokens,
+                vec![
+                    Token::PlusEqual {
+                        lexeme: "+=",
+                        literal: None,
+                        line: 1,
+                        span: span(0, 2),
+                    },
+                    Token::MinusEqual {
+                        lexeme: "-=",
+                        literal: None,
+                        line: 1,
+                        span: span(3, 5),
+                    },
+                    Token::StarEqual {
+                        lexeme: "*=",
+                        literal: None,
+                        line: 1,
+                        span: span(6, 8),
+                    },
+                    Token::SlashEqual {
+                        lexeme: "/=",
+                        literal: None,
+                        line: 1,
+                        span: span(9, 11),
+                    },
+                    Token::PercentEqual {
+                        lexeme: "%=",
+                        literal: None,
+                        line: 1,
+                        span: span(12, 14),
+                    },
+                    Token::Eof {
+                        lexeme: "",
+                        literal: None,
+                        line: 1,
+                        span: span(14, 14),
+                    },
+                ]
+            );
+        }
+
+        #[test]
+        fn scans_increment_and_decrement() {
+            let source = "i++ - --j";
+            let tokens = scan_tokens(FileId(0), source).unwrap();
+            assert_eq!(tokens.len(), 6);
+            assert_eq!(
+                tokens[1],
+                Token::PlusPlus {
+                    lexeme: "++",
+                    literal: None,
+                    line: 1,
+                    span: span(1, 3),
+                }
+            );
+            assert_eq!(
+                tokens[2],
+                Token::Minus {
+                    lexeme: "-",
+                    literal: None,
+                    line: 1,
+                    span: span(4, 5),
+                }
+            );
+            assert_eq!(
+                tokens[3],
+                Token::MinusMinus {
+                    lexeme: "--",
+                    literal: None,
+                    line: 1,
+                    span: span(6, 8),
+                }
+            );
+        }
+
+        #[test]
+        fn scans_ternary_and_modulo() {
+            let source = "n % 2 ? a : b";
+            let tokens = scan_tokens(FileId(0), source).unwrap();
+            assert_eq!(tokens.len(), 8);
+            assert_eq!(
+                tokens[1],
+                Token::Percent {
+                    lexeme: "%",
+                    literal: None,
+                    line: 1,
+                    span: span(2, 3),
+                }
+            );
+            assert_eq!(
+                tokens[3],
+                Token::Question {
+                    lexeme: "?",
+                    literal: None,
+                    line: 1,
+                    span: span(6, 7),
+                }
+            );
+        }
+
+        #[test]
+        fn slash_equal_does_not_start_a_comment() {
+            let source = "x /= 2 // halve it";
+            let tokens = scan_tokens(FileId(0), source).unwrap();
+            assert_eq!(tokens.len(), 4);
+            assert_eq!(
+                tokens[1],
+                Token::SlashEqual {
+                    lexeme: "/=",
+                    literal: None,
+                    line: 1,
+                    span: span(2, 4),
+                }
+            );
+        }
+        #[test]
+        fn scans_bitwise_operators() {
+            let source = "a & b | c ^ ~d";
+            let tokens = scan_tokens(FileId(0), source).unwrap();
+            assert_eq!(tokens.len(), 9);
+            assert_eq!(
+                tokens[1],
+                Token::Ampersand {
+                    lexeme: "&",
+                    literal: None,
+                    line: 1,
+                    span: span(2, 3),
+                }
+            );
+            assert_eq!(
+                tokens[3],
+                Token::Pipe {
+                    lexeme: "|",
+                    literal: None,
+                    line: 1,
+                    span: span(6, 7),
+                }
+            );
+            assert_eq!(
+                tokens[5],
+                Token::Caret {
+                    lexeme: "^",
+                    literal: None,
+                    line: 1,
+                    span: span(10, 11),
+                }
+            );
+            assert_eq!(
+                tokens[6],
+                Token::Tilde {
+                    lexeme: "~",
+                    literal: None,
+                    line: 1,
+                    span: span(12, 13),
+                }
+            );
+        }
+
+        #[test]
+        fn scans_shift_power_and_integer_division() {
+            let source = "<< >> ** ~/";
+            let tokens = scan_tokens(FileId(0), source).unwrap();
+            assert_eq!(
+                tokens,
+                vec![
+                    Token::LessLess {
+                        lexeme: "<<",
+                        literal: None,
+                        line: 1,
+                        span: span(0, 2),
+                    },
+                    Token::GreaterGreater {
+                        lexeme: ">>",
+                        literal: None,
+                        line: 1,
+                        span: span(3, 5),
+                    },
+                    Token::StarStar {
+                        lexeme: "**",
+                        literal: None,
+                        line: 1,
+                        span: span(6, 8),
+                    },
+                    Token::TildeSlash {
+                        lexeme: "~/",
+                        literal: None,
+                        line: 1,
+                        span: span(9, 11),
+                    },
+                    Token::Eof {
+                        lexeme: "",
+                        literal: None,
+                        line: 1,
+                        span: span(11, 11),
+                    },
+                ]
+            );
+        }
+
+        #[test]
+        fn tilde_before_a_comment_is_not_integer_division() {
+            let source = "~// note";
+            let tokens = scan_tokens(FileId(0), source).unwrap();
+            assert_eq!(
+                tokens,
+                vec![
+                    Token::Tilde {
+                        lexeme: "~",
+                        literal: None,
+                        line: 1,
+                        span: span(0, 1),
+                    },
+                    Token::Eof {
+                        lexeme: "",
+                        literal: None,
+                        line: 1,
+                        span: span(8, 8),
+                    },
+                ]
+            );
+        }
+
+        #[test]
+        fn reports_unexpected_characters_with_their_span() {
+            let source = "a @ b";
+            let errors = scan_tokens(FileId(0), source).err().unwrap();
+            assert_eq!(
+                errors,
+                vec![ScanError {
+                    kind: ScanErrorKind::UnexpectedCharacter('@'),
+                    span: span(2, 3),
+                }]
+            );
+        }
+    }
+
+    mod pragmas {
+        use super::span;
+        use crate::{
+            scanner::{scan_pragmas, scan_tokens, Pragma},
+            source_map::FileId,
+        };
+
+        #[test]
+        fn finds_allow_pragmas_in_comments() {
+            let source = "var a; // lox-allow(unused_variables, empty_block)\nprint a;";
+            let tokens = scan_tokens(FileId(0), source).unwrap();
+            assert_eq!(
+                scan_pragmas(source, &tokens),
+                vec![Pragma {
+                    span: span(7, 50),
+                    lints: vec![
+                        ("unused_variables", span(20, 36)),
+                        ("empty_block", span(38, 49)),
+                    ],
+                }]
+            );
+        }
+
+        #[test]
+        fn ignores_ordinary_comments_and_strings() {
+            let source = "// just a note\nprint \"// lox-allow(unused)\"; // lox-allow(unused";
+            let tokens = scan_tokens(FileId(0), source).unwrap();
+            assert!(scan_pragmas(source, &tokens).is_empty());
+        }
+    }
+
+    mod incomplete {
+        use crate::scanner::is_incomplete;
+
+        #[test]
+        fn detects_unclosed_brackets_and_strings() {
+            assert!(is_incomplete("fun f() {"));
+            assert!(is_incomplete("print (1 +"));
+            assert!(is_incomplete("var a = [1,"));
+            assert!(is_incomplete("print \"abc"));
+            assert!(is_incomplete("print (\"abc"));
+        }
+
+        #[test]
+        fn accepts_complete_or_invalid_input() {
+            assert!(!is_incomplete("fun f() { print 1; }"));
+            assert!(!is_incomplete("print \"{\";"));
+            assert!(!is_incomplete("}"));
+            assert!(!is_incomplete(") ( ("));
+            assert!(!is_incomplete(") \"abc"));
+            assert!(!is_incomplete("@ \"abc"));
+            assert!(!is_incomplete("print @;"));
+        }
+    }
+
+    mod keywords {
+        use super::span;
+        use crate::{
+            scanner::{scan_tokens, KEYWORDS},
+            source_map::FileId,
+            token::Token,
+        };
+
+        #[test]
+        fn keywords_scan_to_their_own_tokens() {
+            for keyword in KEYWORDS {
+                let tokens = scan_tokens(FileId(0), keyword).unwrap();
+                assert_eq!(tokens[0].lexeme(), keyword);
+                assert_eq!(tokens[0].kind().to_lowercase(), keyword);
+            }
+            assert!(KEYWORDS.windows(2).all(|pair| pair[0] < pair[1]));
+        }
+
+        #[test]
+        fn scans_exception_keywords() {
+            let source = "try catch finally throw";
+            let tokens = scan_tokens(FileId(0), source).unwrap();
+            assert_eq!(tokens.len(), 5);
+            assert_eq!(
+                tokens[0],
+                Token::Try {
+                    lexeme: "try",
+                    literal: None,
+                    line: 1,
+                    span: span(0, 3),
+                }
+            );
+            assert_eq!(
+                tokens[1],
+                Token::Catch {
+                    lexeme: "catch",
+                    literal: None,
+                    line: 1,
+                    span: span(4, 9),
+                }
+            );
+            assert_eq!(
+                tokens[2],
+                Token::Finally {
+                    lexeme: "finally",
+                    literal: None,
+                    line: 1,
+                    span: span(10, 17),
+                }
+            );
+            assert_eq!(
+                tokens[3],
+                Token::Throw {
+                    lexeme: "throw",
+                    literal: None,
+                    line: 1,
+                    span: span(18, 23),
+                }
+            );
+        }
+
+        #[test]
+        fn scans_labelled_loop_control() {
+            let source = "break outer;\ncontinue;";
+            let tokens = scan_tokens(FileId(0), source).unwrap();
+            assert_eq!(tokens.len(), 6);
+            assert_eq!(
+                tokens[0],
+                Token::Break {
+                    lexeme: "break",
+                    literal: None,
+                    line: 1,
+                    span: span(0, 5),
+                }
+            );
+            assert_eq!(
+                tokens[1],
+                Token::Identifier {
+                    lexeme: "outer",
+                    literal: None,
+                    line: 1,
+                    span: span(6, 11),
+                }
+            );
+            assert_eq!(
+                tokens[3],
+                Token::Continue {
+                    lexeme: "continue",
+                    literal: None,
+                    line: 2,
+                    span: span(13, 21),
+                }
+            );
+        }
+
+        #[test]
+        fn scans_for_in_loop_header() {
+            let source = "for (x in xs)";
+            let tokens = scan_tokens(FileId(0), source).unwrap();
+            assert_eq!(tokens.len(), 7);
+            assert_eq!(
+                tokens[3],
+                Token::In {
+                    lexeme: "in",
+                    literal: None,
+                    line: 1,
+                    span: span(7, 9),
+                }
+            );
+        }
+
+        #[test]
+        fn scans_yield_keyword() {
+            let source = "yield i;";
+            let tokens = scan_tokens(FileId(0), source).unwrap();
+            assert_eq!(tokens.len(), 4);
+            assert_eq!(
+                tokens[0],
+                Token::Yield {
+                    lexeme: "yield",
+                    literal: None,
+                    line: 1,
+                    span: span(0, 5),
+                }
+            );
+        }
+
+        #[test]
+        fn scans_const_declaration() {
+            let source = "const limit = 10;";
+            let tokens = scan_tokens(FileId(0), source).unwrap();
+            assert_eq!(tokens.len(), 6);
+            assert_eq!(
+                tokens[0],
+                Token::Const {
+                    lexeme: "const",
+                    literal: None,
+                    line: 1,
+                    span: span(0, 5),
+                }
+            );
+        }
+
+        #[test]
+        fn scans_module_keywords() {
+            let source = "import { a } from \"mod.lox\";\nexport fun";
+            let tokens = scan_tokens(FileId(0), source).unwrap();
+            assert_eq!(tokens.len(), 10);
+            assert_eq!(
+                tokens[0],
+                Token::Import {
+                    lexeme: "import",
+                    literal: None,
+                    line: 1,
+                    span: span(0, 6),
+                }
+            );
+            assert_eq!(
+                tokens[7],
+                Token::Export {
+                    lexeme: "export",
+                    literal: None,
+                    line: 2,
+                    span: span(29, 35),
+                }
+            );
+        }
+
+        #[test]
+        fn module_clause_words_stay_identifiers() {
+            let source = "from as";
+            let tokens = scan_tokens(FileId(0), source).unwrap();
+            assert_eq!(tokens.len(), 3);
+            assert_eq!(
+                tokens[0],
+                Token::Identifier {
+                    lexeme: "from",
+                    literal: None,
+                    line: 1,
+                    span: span(0, 4),
+                }
+            );
+            assert_eq!(
+                tokens[1],
+                Token::Identifier {
+                    lexeme: "as",
+                    literal: None,
+                    line: 1,
+                    span: span(5, 7),
+                }
+            );
+        }
+
+        #[test]
+        fn keyword_prefixes_are_identifiers() {
+            let source = "trying";
+            let tokens = scan_tokens(FileId(0), source).unwrap();
+            assert_eq!(tokens.len(), 2);
+            assert_eq!(
+                tokens[0],
+                Token::Identifier {
+                    lexeme: "trying",
+                    literal: None,
+                    line: 1,
+                    span: span(0, 6),
                 }
             );
         }
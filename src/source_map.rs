@@ -0,0 +1,179 @@
+use std::fmt;
+
+/// Identifies one source text owned by a `SourceMap`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileId(pub usize);
+
+/// A byte range within one file of a `SourceMap`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub file: FileId,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(file: FileId, start: usize, end: usize) -> Self {
+        Span { file, start, end }
+    }
+}
+
+/// A human-readable position: file name plus 1-based line and column.
+#[derive(Debug, PartialEq)]
+pub struct Location<'a> {
+    pub file: &'a str,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Location<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+pub struct SourceFile {
+    name: String,
+    source: String,
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    fn new(name: String, source: String) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(idx, _)| idx + 1))
+            .collect();
+        SourceFile {
+            name,
+            source,
+            line_starts,
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the byte offset at which a 1-based line starts.
+    pub fn line_start(&self, line: usize) -> usize {
+        self.line_starts[line - 1]
+    }
+
+    /// Returns the text of a 1-based line without its line terminator.
+    pub fn line(&self, line: usize) -> &str {
+        let start = self.line_start(line);
+        let end = self
+            .line_starts
+            .get(line)
+            .copied()
+            .unwrap_or(self.source.len());
+        self.source[start..end].trim_end_matches(['\n', '\r'])
+    }
+
+    /// Maps a byte offset to its 1-based line and column, counting columns
+    /// in characters rather than bytes.
+    pub fn location(&self, offset: usize) -> Location<'_> {
+        let line = self.line_starts.partition_point(|&start| start <= offset);
+        let line_start = self.line_starts[line - 1];
+        Location {
+            file: &self.name,
+            line,
+            column: self.source[line_start..offset].chars().count() + 1,
+        }
+    }
+}
+
+/// Owns every source text loaded in a session (scripts, REPL entries, ...)
+/// so spans can be resolved back to a file, line and column.
+#[derive(Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        SourceMap::default()
+    }
+
+    pub fn add(&mut self, name: impl Into<String>, source: impl Into<String>) -> FileId {
+        self.files.push(SourceFile::new(name.into(), source.into()));
+        FileId(self.files.len() - 1)
+    }
+
+    pub fn get(&self, file: FileId) -> &SourceFile {
+        &self.files[file.0]
+    }
+
+    pub fn location(&self, span: Span) -> Location<'_> {
+        self.get(span.file).location(span.start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::source_map::{FileId, Location, SourceMap, Span};
+
+    #[test]
+    fn assigns_sequential_file_ids() {
+        let mut sources = SourceMap::new();
+        let first = sources.add("main.lox", "print 1;");
+        let second = sources.add("<repl:1>", "print 2;");
+        assert_eq!(first, FileId(0));
+        assert_eq!(second, FileId(1));
+        assert_eq!(sources.get(second).location(0).file, "<repl:1>");
+        assert_eq!(sources.get(second).source(), "print 2;");
+    }
+
+    #[test]
+    fn maps_offsets_to_lines_and_columns() {
+        let mut sources = SourceMap::new();
+        let file = sources.add("main.lox", "var a;\nvar b;\n\nprint a;");
+        assert_eq!(
+            sources.get(file).location(0),
+            Location {
+                file: "main.lox",
+                line: 1,
+                column: 1,
+            }
+        );
+        assert_eq!(
+            sources.get(file).location(11),
+            Location {
+                file: "main.lox",
+                line: 2,
+                column: 5,
+            }
+        );
+        assert_eq!(
+            sources.get(file).location(15),
+            Location {
+                file: "main.lox",
+                line: 4,
+                column: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn counts_columns_in_characters() {
+        let mut sources = SourceMap::new();
+        let file = sources.add("main.lox", "\"héllo\" @");
+        let span = Span::new(file, 9, 10);
+        assert_eq!(sources.location(span).to_string(), "main.lox:1:9");
+    }
+
+    #[test]
+    fn returns_line_text_without_terminators() {
+        let mut sources = SourceMap::new();
+        let file = sources.add("main.lox", "var a;\r\nprint a;");
+        assert_eq!(sources.get(file).line(1), "var a;");
+        assert_eq!(sources.get(file).line(2), "print a;");
+    }
+
+    #[test]
+    fn locates_the_end_of_the_source() {
+        let mut sources = SourceMap::new();
+        let file = sources.add("main.lox", "a\n");
+        assert_eq!(sources.get(file).location(2).to_string(), "main.lox:2:1");
+    }
+}
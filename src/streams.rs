@@ -0,0 +1,32 @@
+use crate::diagnostic::ErrorFormat;
+use std::io::{self, BufRead, IsTerminal, Write};
+
+/// The sinks and source the interpreter talks to instead of the process's
+/// standard streams, so embedders and tests can capture or redirect them.
+pub struct Streams {
+    /// Program output: the token dump today, `print` once it exists.
+    pub out: Box<dyn Write>,
+    /// Diagnostics: scan errors and usage messages.
+    pub err: Box<dyn Write>,
+    /// Input for the REPL and for input natives.
+    pub input: Box<dyn BufRead>,
+    /// Whether diagnostics written to `err` may use ANSI colors.
+    pub color: bool,
+    /// Whether `input` is the process's terminal. The prompt then edits lines
+    /// in raw mode; piped input is read as a whole program instead.
+    pub interactive: bool,
+    pub error_format: ErrorFormat,
+}
+
+impl Streams {
+    pub fn stdio() -> Self {
+        Streams {
+            out: Box::new(io::stdout()),
+            err: Box::new(io::stderr()),
+            input: Box::new(io::stdin().lock()),
+            color: io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+            interactive: io::stdin().is_terminal(),
+            error_format: ErrorFormat::Human,
+        }
+    }
+}
@@ -1,3 +1,8 @@
+use crate::source_map::Span;
+
+/// A scanned token. `line` is the line the token ends on, so for a string
+/// spanning several lines it is the closing line; look `span` up in the
+/// `SourceMap` for where the token starts.
 #[derive(Debug, PartialEq)]
 pub enum Token<'a> {
     // Single-character tokens.
@@ -5,56 +10,115 @@ pub enum Token<'a> {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
     },
     RightParen {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
     },
     LeftBrace {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
     },
     RightBrace {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
+    },
+    LeftBracket {
+        lexeme: &'a str,
+        literal: Option<&'a str>,
+        line: usize,
+        span: Span,
+    },
+    RightBracket {
+        lexeme: &'a str,
+        literal: Option<&'a str>,
+        line: usize,
+        span: Span,
     },
     Comma {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
+    },
+    Colon {
+        lexeme: &'a str,
+        literal: Option<&'a str>,
+        line: usize,
+        span: Span,
     },
     Dot {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
     },
     Minus {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
+    },
+    Percent {
+        lexeme: &'a str,
+        literal: Option<&'a str>,
+        line: usize,
+        span: Span,
     },
     Plus {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
+    },
+    Question {
+        lexeme: &'a str,
+        literal: Option<&'a str>,
+        line: usize,
+        span: Span,
     },
     Semicolon {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
     },
     Slash {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
     },
     Star {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
+    },
+    Ampersand {
+        lexeme: &'a str,
+        literal: Option<&'a str>,
+        line: usize,
+        span: Span,
+    },
+    Caret {
+        lexeme: &'a str,
+        literal: Option<&'a str>,
+        line: usize,
+        span: Span,
+    },
+    Pipe {
+        lexeme: &'a str,
+        literal: Option<&'a str>,
+        line: usize,
+        span: Span,
     },
 
     // One or two character tokens.
@@ -62,145 +126,552 @@ pub enum Token<'a> {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
     },
     BangEqual {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
     },
     Equal {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
     },
     EqualEqual {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
+    },
+    EqualGreater {
+        lexeme: &'a str,
+        literal: Option<&'a str>,
+        line: usize,
+        span: Span,
     },
     Greater {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
     },
     GreaterEqual {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
+    },
+    GreaterGreater {
+        lexeme: &'a str,
+        literal: Option<&'a str>,
+        line: usize,
+        span: Span,
     },
     Less {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
     },
     LessEqual {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
+    },
+    LessLess {
+        lexeme: &'a str,
+        literal: Option<&'a str>,
+        line: usize,
+        span: Span,
+    },
+    MinusEqual {
+        lexeme: &'a str,
+        literal: Option<&'a str>,
+        line: usize,
+        span: Span,
+    },
+    MinusMinus {
+        lexeme: &'a str,
+        literal: Option<&'a str>,
+        line: usize,
+        span: Span,
+    },
+    PercentEqual {
+        lexeme: &'a str,
+        literal: Option<&'a str>,
+        line: usize,
+        span: Span,
+    },
+    PlusEqual {
+        lexeme: &'a str,
+        literal: Option<&'a str>,
+        line: usize,
+        span: Span,
+    },
+    PlusPlus {
+        lexeme: &'a str,
+        literal: Option<&'a str>,
+        line: usize,
+        span: Span,
+    },
+    SlashEqual {
+        lexeme: &'a str,
+        literal: Option<&'a str>,
+        line: usize,
+        span: Span,
+    },
+    StarEqual {
+        lexeme: &'a str,
+        literal: Option<&'a str>,
+        line: usize,
+        span: Span,
+    },
+    StarStar {
+        lexeme: &'a str,
+        literal: Option<&'a str>,
+        line: usize,
+        span: Span,
+    },
+    Tilde {
+        lexeme: &'a str,
+        literal: Option<&'a str>,
+        line: usize,
+        span: Span,
+    },
+    TildeSlash {
+        lexeme: &'a str,
+        literal: Option<&'a str>,
+        line: usize,
+        span: Span,
     },
     String {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
     },
     Integer {
         lexeme: &'a str,
         literal: Option<i64>,
         line: usize,
+        span: Span,
     },
     Float {
         lexeme: &'a str,
         literal: Option<f64>,
         line: usize,
+        span: Span,
     },
     Identifier {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
     },
     And {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
+    },
+    Break {
+        lexeme: &'a str,
+        literal: Option<&'a str>,
+        line: usize,
+        span: Span,
+    },
+    Catch {
+        lexeme: &'a str,
+        literal: Option<&'a str>,
+        line: usize,
+        span: Span,
     },
     Class {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
+    },
+    Const {
+        lexeme: &'a str,
+        literal: Option<&'a str>,
+        line: usize,
+        span: Span,
+    },
+    Continue {
+        lexeme: &'a str,
+        literal: Option<&'a str>,
+        line: usize,
+        span: Span,
     },
     Else {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
+    },
+    Export {
+        lexeme: &'a str,
+        literal: Option<&'a str>,
+        line: usize,
+        span: Span,
     },
     False {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
+    },
+    Finally {
+        lexeme: &'a str,
+        literal: Option<&'a str>,
+        line: usize,
+        span: Span,
     },
     Fun {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
     },
     For {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
     },
     If {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
+    },
+    Import {
+        lexeme: &'a str,
+        literal: Option<&'a str>,
+        line: usize,
+        span: Span,
+    },
+    In {
+        lexeme: &'a str,
+        literal: Option<&'a str>,
+        line: usize,
+        span: Span,
     },
     Nil {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
     },
     Or {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
     },
     Print {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
     },
     Return {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
     },
     Super {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
     },
     This {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
+    },
+    Throw {
+        lexeme: &'a str,
+        literal: Option<&'a str>,
+        line: usize,
+        span: Span,
     },
     True {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
+    },
+    Try {
+        lexeme: &'a str,
+        literal: Option<&'a str>,
+        line: usize,
+        span: Span,
     },
     Var {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
     },
     While {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
+    },
+    Yield {
+        lexeme: &'a str,
+        literal: Option<&'a str>,
+        line: usize,
+        span: Span,
     },
     Eof {
         lexeme: &'a str,
         literal: Option<&'a str>,
         line: usize,
+        span: Span,
     },
 }
+
+impl<'a> Token<'a> {
+    /// The variant name, e.g. `"LeftParen"`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Token::LeftParen { .. } => "LeftParen",
+            Token::RightParen { .. } => "RightParen",
+            Token::LeftBrace { .. } => "LeftBrace",
+            Token::RightBrace { .. } => "RightBrace",
+            Token::LeftBracket { .. } => "LeftBracket",
+            Token::RightBracket { .. } => "RightBracket",
+            Token::Comma { .. } => "Comma",
+            Token::Colon { .. } => "Colon",
+            Token::Dot { .. } => "Dot",
+            Token::Minus { .. } => "Minus",
+            Token::Percent { .. } => "Percent",
+            Token::Plus { .. } => "Plus",
+            Token::Question { .. } => "Question",
+            Token::Semicolon { .. } => "Semicolon",
+            Token::Slash { .. } => "Slash",
+            Token::Star { .. } => "Star",
+            Token::Ampersand { .. } => "Ampersand",
+            Token::Caret { .. } => "Caret",
+            Token::Pipe { .. } => "Pipe",
+            Token::Bang { .. } => "Bang",
+            Token::BangEqual { .. } => "BangEqual",
+            Token::Equal { .. } => "Equal",
+            Token::EqualEqual { .. } => "EqualEqual",
+            Token::EqualGreater { .. } => "EqualGreater",
+            Token::Greater { .. } => "Greater",
+            Token::GreaterEqual { .. } => "GreaterEqual",
+            Token::GreaterGreater { .. } => "GreaterGreater",
+            Token::Less { .. } => "Less",
+            Token::LessEqual { .. } => "LessEqual",
+            Token::LessLess { .. } => "LessLess",
+            Token::MinusEqual { .. } => "MinusEqual",
+            Token::MinusMinus { .. } => "MinusMinus",
+            Token::PercentEqual { .. } => "PercentEqual",
+            Token::PlusEqual { .. } => "PlusEqual",
+            Token::PlusPlus { .. } => "PlusPlus",
+            Token::SlashEqual { .. } => "SlashEqual",
+            Token::StarEqual { .. } => "StarEqual",
+            Token::StarStar { .. } => "StarStar",
+            Token::Tilde { .. } => "Tilde",
+            Token::TildeSlash { .. } => "TildeSlash",
+            Token::String { .. } => "String",
+            Token::Integer { .. } => "Integer",
+            Token::Float { .. } => "Float",
+            Token::Identifier { .. } => "Identifier",
+            Token::And { .. } => "And",
+            Token::Break { .. } => "Break",
+            Token::Catch { .. } => "Catch",
+            Token::Class { .. } => "Class",
+            Token::Const { .. } => "Const",
+            Token::Continue { .. } => "Continue",
+            Token::Else { .. } => "Else",
+            Token::Export { .. } => "Export",
+            Token::False { .. } => "False",
+            Token::Finally { .. } => "Finally",
+            Token::Fun { .. } => "Fun",
+            Token::For { .. } => "For",
+            Token::If { .. } => "If",
+            Token::Import { .. } => "Import",
+            Token::In { .. } => "In",
+            Token::Nil { .. } => "Nil",
+            Token::Or { .. } => "Or",
+            Token::Print { .. } => "Print",
+            Token::Return { .. } => "Return",
+            Token::Super { .. } => "Super",
+            Token::This { .. } => "This",
+            Token::Throw { .. } => "Throw",
+            Token::True { .. } => "True",
+            Token::Try { .. } => "Try",
+            Token::Var { .. } => "Var",
+            Token::While { .. } => "While",
+            Token::Yield { .. } => "Yield",
+            Token::Eof { .. } => "Eof",
+        }
+    }
+
+    pub fn lexeme(&self) -> &'a str {
+        match self {
+            Token::LeftParen { lexeme, .. }
+            | Token::RightParen { lexeme, .. }
+            | Token::LeftBrace { lexeme, .. }
+            | Token::RightBrace { lexeme, .. }
+            | Token::LeftBracket { lexeme, .. }
+            | Token::RightBracket { lexeme, .. }
+            | Token::Comma { lexeme, .. }
+            | Token::Colon { lexeme, .. }
+            | Token::Dot { lexeme, .. }
+            | Token::Minus { lexeme, .. }
+            | Token::Percent { lexeme, .. }
+            | Token::Plus { lexeme, .. }
+            | Token::Question { lexeme, .. }
+            | Token::Semicolon { lexeme, .. }
+            | Token::Slash { lexeme, .. }
+            | Token::Star { lexeme, .. }
+            | Token::Ampersand { lexeme, .. }
+            | Token::Caret { lexeme, .. }
+            | Token::Pipe { lexeme, .. }
+            | Token::Bang { lexeme, .. }
+            | Token::BangEqual { lexeme, .. }
+            | Token::Equal { lexeme, .. }
+            | Token::EqualEqual { lexeme, .. }
+            | Token::EqualGreater { lexeme, .. }
+            | Token::Greater { lexeme, .. }
+            | Token::GreaterEqual { lexeme, .. }
+            | Token::GreaterGreater { lexeme, .. }
+            | Token::Less { lexeme, .. }
+            | Token::LessEqual { lexeme, .. }
+            | Token::LessLess { lexeme, .. }
+            | Token::MinusEqual { lexeme, .. }
+            | Token::MinusMinus { lexeme, .. }
+            | Token::PercentEqual { lexeme, .. }
+            | Token::PlusEqual { lexeme, .. }
+            | Token::PlusPlus { lexeme, .. }
+            | Token::SlashEqual { lexeme, .. }
+            | Token::StarEqual { lexeme, .. }
+            | Token::StarStar { lexeme, .. }
+            | Token::Tilde { lexeme, .. }
+            | Token::TildeSlash { lexeme, .. }
+            | Token::String { lexeme, .. }
+            | Token::Integer { lexeme, .. }
+            | Token::Float { lexeme, .. }
+            | Token::Identifier { lexeme, .. }
+            | Token::And { lexeme, .. }
+            | Token::Break { lexeme, .. }
+            | Token::Catch { lexeme, .. }
+            | Token::Class { lexeme, .. }
+            | Token::Const { lexeme, .. }
+            | Token::Continue { lexeme, .. }
+            | Token::Else { lexeme, .. }
+            | Token::Export { lexeme, .. }
+            | Token::False { lexeme, .. }
+            | Token::Finally { lexeme, .. }
+            | Token::Fun { lexeme, .. }
+            | Token::For { lexeme, .. }
+            | Token::If { lexeme, .. }
+            | Token::Import { lexeme, .. }
+            | Token::In { lexeme, .. }
+            | Token::Nil { lexeme, .. }
+            | Token::Or { lexeme, .. }
+            | Token::Print { lexeme, .. }
+            | Token::Return { lexeme, .. }
+            | Token::Super { lexeme, .. }
+            | Token::This { lexeme, .. }
+            | Token::Throw { lexeme, .. }
+            | Token::True { lexeme, .. }
+            | Token::Try { lexeme, .. }
+            | Token::Var { lexeme, .. }
+            | Token::While { lexeme, .. }
+            | Token::Yield { lexeme, .. }
+            | Token::Eof { lexeme, .. } => lexeme,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Token::LeftParen { span, .. }
+            | Token::RightParen { span, .. }
+            | Token::LeftBrace { span, .. }
+            | Token::RightBrace { span, .. }
+            | Token::LeftBracket { span, .. }
+            | Token::RightBracket { span, .. }
+            | Token::Comma { span, .. }
+            | Token::Colon { span, .. }
+            | Token::Dot { span, .. }
+            | Token::Minus { span, .. }
+            | Token::Percent { span, .. }
+            | Token::Plus { span, .. }
+            | Token::Question { span, .. }
+            | Token::Semicolon { span, .. }
+            | Token::Slash { span, .. }
+            | Token::Star { span, .. }
+            | Token::Ampersand { span, .. }
+            | Token::Caret { span, .. }
+            | Token::Pipe { span, .. }
+            | Token::Bang { span, .. }
+            | Token::BangEqual { span, .. }
+            | Token::Equal { span, .. }
+            | Token::EqualEqual { span, .. }
+            | Token::EqualGreater { span, .. }
+            | Token::Greater { span, .. }
+            | Token::GreaterEqual { span, .. }
+            | Token::GreaterGreater { span, .. }
+            | Token::Less { span, .. }
+            | Token::LessEqual { span, .. }
+            | Token::LessLess { span, .. }
+            | Token::MinusEqual { span, .. }
+            | Token::MinusMinus { span, .. }
+            | Token::PercentEqual { span, .. }
+            | Token::PlusEqual { span, .. }
+            | Token::PlusPlus { span, .. }
+            | Token::SlashEqual { span, .. }
+            | Token::StarEqual { span, .. }
+            | Token::StarStar { span, .. }
+            | Token::Tilde { span, .. }
+            | Token::TildeSlash { span, .. }
+            | Token::String { span, .. }
+            | Token::Integer { span, .. }
+            | Token::Float { span, .. }
+            | Token::Identifier { span, .. }
+            | Token::And { span, .. }
+            | Token::Break { span, .. }
+            | Token::Catch { span, .. }
+            | Token::Class { span, .. }
+            | Token::Const { span, .. }
+            | Token::Continue { span, .. }
+            | Token::Else { span, .. }
+            | Token::Export { span, .. }
+            | Token::False { span, .. }
+            | Token::Finally { span, .. }
+            | Token::Fun { span, .. }
+            | Token::For { span, .. }
+            | Token::If { span, .. }
+            | Token::Import { span, .. }
+            | Token::In { span, .. }
+            | Token::Nil { span, .. }
+            | Token::Or { span, .. }
+            | Token::Print { span, .. }
+            | Token::Return { span, .. }
+            | Token::Super { span, .. }
+            | Token::This { span, .. }
+            | Token::Throw { span, .. }
+            | Token::True { span, .. }
+            | Token::Try { span, .. }
+            | Token::Var { span, .. }
+            | Token::While { span, .. }
+            | Token::Yield { span, .. }
+            | Token::Eof { span, .. } => *span,
+        }
+    }
+}